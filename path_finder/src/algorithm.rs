@@ -0,0 +1,77 @@
+use std::{fmt, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::error::PathError;
+
+/// The path finding algorithms a route can be computed with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    #[default]
+    Dijkstra,
+    AStar,
+    /// Can't apply turn restrictions or turn costs
+    BellmanFord,
+    /// Can't apply turn restrictions or turn costs
+    Bidirectional,
+    /// `A*` with landmark lower bounds, needs the landmarks of the graph
+    Alt,
+    /// Needs the contraction hierarchy of the graph, which is built without turn restrictions and
+    /// turn costs so it can't apply them
+    ContractionHierarchy,
+}
+
+impl FromStr for Algorithm {
+    type Err = PathError;
+
+    /// Parses the identifiers used by the frontend, e.g. `a_star`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dijkstra" => Ok(Algorithm::Dijkstra),
+            "a_star" => Ok(Algorithm::AStar),
+            "bellman_ford" => Ok(Algorithm::BellmanFord),
+            "bidirectional" => Ok(Algorithm::Bidirectional),
+            "alt" => Ok(Algorithm::Alt),
+            "contraction_hierarchy" => Ok(Algorithm::ContractionHierarchy),
+            other => Err(PathError::UnknownAlgorithm(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Algorithm::Dijkstra => "dijkstra",
+            Algorithm::AStar => "a_star",
+            Algorithm::BellmanFord => "bellman_ford",
+            Algorithm::Bidirectional => "bidirectional",
+            Algorithm::Alt => "alt",
+            Algorithm::ContractionHierarchy => "contraction_hierarchy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_algorithm() {
+        for algorithm in [
+            Algorithm::Dijkstra,
+            Algorithm::AStar,
+            Algorithm::BellmanFord,
+            Algorithm::Bidirectional,
+            Algorithm::Alt,
+            Algorithm::ContractionHierarchy,
+        ] {
+            assert_eq!(algorithm.to_string().parse::<Algorithm>(), Ok(algorithm));
+        }
+        assert_eq!(
+            "floyd".parse::<Algorithm>(),
+            Err(PathError::UnknownAlgorithm("floyd".to_string()))
+        );
+    }
+}
@@ -1,15 +1,152 @@
-use std::{env, process};
+use std::{env, fs, process};
 
-use path_finder::Graph;
+use path_finder::{ExportFormat, Graph, Metric, Profile, SearchOptions};
+
+const USAGE: &str = "Usage:
+  ./path_finder <json_file>
+  ./path_finder ch <json_file> <output_file> [profile] [metric]
+  ./path_finder matrix <json_file> <sources_file> <targets_file> <output_file> [profile] [metric]
+  ./path_finder route <json_file> <from> <to> <output_file> [profile] [metric] [format]
+
+Sources and targets files have one `lat,lon` point per line
+The matrix is written as JSON when the output file ends in .json and as CSV otherwise
+Route ends are `lat,lon` points and the format is one of geojson, gpx, gpx_route or kml,
+by default the one of the output file extension";
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: ./path_finder <json_file>");
+    match args.get(1).map(String::as_str) {
+        Some("ch") => contract(&args[2..]),
+        Some("matrix") => matrix(&args[2..]),
+        Some("route") => route(&args[2..]),
+        Some(json_file_path) => {
+            let graph =
+                Graph::from_json_file(json_file_path).expect("Failed to parse json to graph");
+            println!("{:#?}", graph);
+        }
+        None => exit_with_usage(),
+    }
+}
+
+/// Builds the graph with its contraction hierarchy and writes it out, ready for
+/// `load_contracted_graph`
+fn contract(args: &[String]) {
+    let [json_file_path, output_path, rest @ ..] = args else {
+        exit_with_usage();
+    };
+    let profile: Profile = parse_or_exit(rest.first().map_or("default", String::as_str));
+    let metric: Metric = parse_or_exit(rest.get(1).map_or("shortest", String::as_str));
+
+    let mut graph = Graph::from_json_file_with_profile(json_file_path, &profile)
+        .expect("Failed to parse json to graph");
+    graph.build_contraction_hierarchy(metric);
+
+    if let Some(hierarchy) = graph.contraction_hierarchy() {
+        println!(
+            "Contracted with {} shortcuts for the {} metric",
+            hierarchy.shortcut_count(),
+            metric
+        );
+    }
+    fs::write(output_path, graph.to_json()).expect("Failed to write the contracted graph");
+}
+
+/// Writes the costs between the nodes nearest to the source and target points
+fn matrix(args: &[String]) {
+    let [json_file_path, sources_path, targets_path, output_path, rest @ ..] = args else {
+        exit_with_usage();
+    };
+    let profile: Profile = parse_or_exit(rest.first().map_or("default", String::as_str));
+    let metric: Metric = parse_or_exit(rest.get(1).map_or("shortest", String::as_str));
+
+    let graph = Graph::from_json_file_with_profile(json_file_path, &profile)
+        .expect("Failed to parse json to graph");
+    let sources = graph
+        .snap_points(&read_points(sources_path))
+        .expect("Failed to snap the sources");
+    let targets = graph
+        .snap_points(&read_points(targets_path))
+        .expect("Failed to snap the targets");
+    let matrix = graph.distance_matrix_with(&sources, &targets, metric);
+
+    let output = if output_path.ends_with(".json") {
+        matrix.to_json()
+    } else {
+        matrix.to_csv()
+    };
+    fs::write(output_path, output).expect("Failed to write the matrix");
+}
+
+/// Writes the route between the nodes nearest to the two points as GeoJSON, GPX or KML
+fn route(args: &[String]) {
+    let [json_file_path, from, to, output_path, rest @ ..] = args else {
+        exit_with_usage();
+    };
+    let profile: Profile = parse_or_exit(rest.first().map_or("default", String::as_str));
+    let metric: Metric = parse_or_exit(rest.get(1).map_or("shortest", String::as_str));
+    let format: ExportFormat = match rest.get(2) {
+        Some(format) => parse_or_exit(format),
+        None => output_path
+            .rsplit_once('.')
+            .and_then(|(_, extension)| ExportFormat::from_extension(extension))
+            .unwrap_or_else(|| exit_with_usage()),
+    };
+
+    let graph = Graph::from_json_file_with_profile(json_file_path, &profile)
+        .expect("Failed to parse json to graph");
+    let nodes = graph
+        .snap_points(&[parse_point(from), parse_point(to)])
+        .expect("Failed to snap the route ends");
+    let options = SearchOptions {
+        metric,
+        ..Default::default()
+    };
+    let route = graph
+        .search(nodes[0], nodes[1], &options)
+        .expect("Failed to search the route")
+        .route;
+
+    if route.is_empty() {
+        eprintln!("No route from node {} to node {}", nodes[0], nodes[1]);
         process::exit(1);
     }
-    let json_file_path = &args[1];
+    println!(
+        "Route of {} nodes, {:.0} m and {:.0} s",
+        route.nodes.len(),
+        route.distance,
+        route.duration
+    );
+    fs::write(output_path, route.export(format, "Route")).expect("Failed to write the route");
+}
+
+/// Reads one `lat,lon` point per line, skipping blank lines
+fn read_points(path: &str) -> Vec<(f64, f64)> {
+    let content = fs::read_to_string(path).expect("Failed to read the points");
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_point)
+        .collect()
+}
+
+fn parse_point(point: &str) -> (f64, f64) {
+    let (lat, lon) = point.split_once(',').unwrap_or_else(|| exit_with_usage());
+    (parse_or_exit(lat.trim()), parse_or_exit(lon.trim()))
+}
+
+fn parse_or_exit<T>(value: &str) -> T
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    value.parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })
+}
 
-    let graph = Graph::from_json_file(json_file_path).expect("Failed to parse json to graph");
-    println!("{:#?}", graph);
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
 }
@@ -0,0 +1,101 @@
+use std::{error::Error, fmt};
+
+use crate::{algorithm::Algorithm, metric::Metric};
+
+/// Errors returned by the path finding algorithms
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathError {
+    /// A negative cycle is reachable from the start node, containing the given node
+    NegativeCycle(u64),
+    /// The algorithm identifier does not match any supported algorithm
+    UnknownAlgorithm(String),
+    /// The profile name does not match any built in profile
+    UnknownProfile(String),
+    /// The metric name is neither `shortest` nor `fastest`
+    UnknownMetric(String),
+    /// The graph has no contraction hierarchy built for the metric
+    MissingContractionHierarchy(Metric),
+    /// The graph has no landmarks built for the metric
+    MissingLandmarks(Metric),
+    /// The algorithm can't apply turn restrictions or turn costs, which the graph or the options
+    /// call for
+    UnsupportedWithRestrictions(Algorithm),
+    /// The landmark strategy is neither `farthest` nor `avoid`
+    UnknownLandmarkStrategy(String),
+    /// A multi stop route needs at least a start and an end, holds the number given
+    TooFewWaypoints(usize),
+    /// The graph has no node to snap the waypoint at this index to
+    NoNearbyNode(usize),
+    /// The end of the leg at index `leg` can't be reached from its start
+    UnreachableLeg { leg: usize, from: u64, to: u64 },
+    /// An isochrone needs at least one threshold
+    NoThresholds,
+    /// Isochrone thresholds have to be positive numbers
+    InvalidThreshold(f32),
+    /// The export format is not one of `geojson`, `gpx`, `gpx_route` or `kml`
+    UnknownExportFormat(String),
+    /// Encoded polylines are only produced at precision 5 or 6, holds the precision asked for
+    UnsupportedPrecision(u32),
+    /// The encoded polyline has an invalid value starting at this byte offset
+    InvalidPolyline(usize),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::NegativeCycle(node) => {
+                write!(f, "negative cycle detected through node {}", node)
+            }
+            PathError::UnknownAlgorithm(name) => write!(f, "unknown algorithm `{}`", name),
+            PathError::UnknownProfile(name) => write!(f, "unknown profile `{}`", name),
+            PathError::UnknownMetric(name) => write!(f, "unknown metric `{}`", name),
+            PathError::MissingContractionHierarchy(metric) => {
+                write!(
+                    f,
+                    "no contraction hierarchy built for the {} metric",
+                    metric
+                )
+            }
+            PathError::MissingLandmarks(metric) => {
+                write!(f, "no landmarks built for the {} metric", metric)
+            }
+            PathError::UnsupportedWithRestrictions(algorithm) => write!(
+                f,
+                "{} can't apply turn restrictions or turn costs, \
+                 use dijkstra, a_star or alt or ignore the restrictions",
+                algorithm
+            ),
+            PathError::UnknownLandmarkStrategy(name) => {
+                write!(f, "unknown landmark strategy `{}`", name)
+            }
+            PathError::TooFewWaypoints(count) => {
+                write!(f, "at least 2 waypoints are needed, got {}", count)
+            }
+            PathError::NoNearbyNode(index) => write!(f, "no node near waypoint {}", index),
+            PathError::UnreachableLeg { leg, from, to } => {
+                write!(
+                    f,
+                    "leg {} is unreachable, no path from node {} to node {}",
+                    leg, from, to
+                )
+            }
+            PathError::NoThresholds => write!(f, "at least 1 threshold is needed"),
+            PathError::InvalidThreshold(threshold) => {
+                write!(f, "threshold {} is not a positive number", threshold)
+            }
+            PathError::UnknownExportFormat(name) => write!(f, "unknown export format `{}`", name),
+            PathError::UnsupportedPrecision(precision) => {
+                write!(
+                    f,
+                    "polyline precision {} is not supported, use 5 or 6",
+                    precision
+                )
+            }
+            PathError::InvalidPolyline(offset) => {
+                write!(f, "invalid polyline value at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for PathError {}
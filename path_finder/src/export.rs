@@ -0,0 +1,295 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{error::PathError, graph::Route};
+
+/// File formats a route can be written in for other tools
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// A `LineString` feature, for QGIS and web maps
+    #[default]
+    GeoJson,
+    /// A GPX track, recorded breadcrumbs most GPS units can show and follow
+    Gpx,
+    /// A GPX route, points the GPS unit navigates between
+    GpxRoute,
+    /// A KML placemark, for Google Earth
+    Kml,
+}
+
+impl ExportFormat {
+    /// The format usually meant by a file extension, without the dot
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "geojson" | "json" => Some(ExportFormat::GeoJson),
+            "gpx" => Some(ExportFormat::Gpx),
+            "kml" => Some(ExportFormat::Kml),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::GeoJson => "geojson",
+            ExportFormat::Gpx | ExportFormat::GpxRoute => "gpx",
+            ExportFormat::Kml => "kml",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::GeoJson => "application/geo+json",
+            ExportFormat::Gpx | ExportFormat::GpxRoute => "application/gpx+xml",
+            ExportFormat::Kml => "application/vnd.google-earth.kml+xml",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "geojson" => Ok(ExportFormat::GeoJson),
+            "gpx" => Ok(ExportFormat::Gpx),
+            "gpx_route" => Ok(ExportFormat::GpxRoute),
+            "kml" => Ok(ExportFormat::Kml),
+            other => Err(PathError::UnknownExportFormat(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExportFormat::GeoJson => "geojson",
+            ExportFormat::Gpx => "gpx",
+            ExportFormat::GpxRoute => "gpx_route",
+            ExportFormat::Kml => "kml",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Route {
+    /// The route written in the format, named `name` where the format has names
+    pub fn export(&self, format: ExportFormat, name: &str) -> String {
+        match format {
+            ExportFormat::GeoJson => {
+                let mut feature = self.to_geojson();
+                feature["properties"]["name"] = json!(name);
+                serde_json::to_string_pretty(&feature).unwrap()
+            }
+            ExportFormat::Gpx => self.to_gpx(name, false),
+            ExportFormat::GpxRoute => self.to_gpx(name, true),
+            ExportFormat::Kml => self.to_kml(name),
+        }
+    }
+
+    /// A GeoJSON `Feature` with a `LineString` through the nodes and the totals as properties
+    /// The geometry is `null` when the route has fewer than 2 nodes
+    pub fn to_geojson(&self) -> Value {
+        let geometry = match self.nodes.len() {
+            0 | 1 => Value::Null,
+            _ => {
+                let coordinates: Vec<[f64; 2]> =
+                    self.nodes.iter().map(|node| [node.lon, node.lat]).collect();
+                json!({ "type": "LineString", "coordinates": coordinates })
+            }
+        };
+
+        json!({
+            "type": "Feature",
+            "properties": {
+                "metric": self.metric,
+                "cost": self.cost,
+                "distance": self.distance,
+                "duration": self.duration,
+                "nodes": self.node_ids(),
+            },
+            "geometry": geometry,
+        })
+    }
+
+    /// A GPX 1.1 document with the nodes as a track, or as a route when `as_route` is set
+    pub fn to_gpx(&self, name: &str, as_route: bool) -> String {
+        let (outer, point) = if as_route {
+            ("rte", "rtept")
+        } else {
+            ("trk", "trkpt")
+        };
+        let mut gpx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        gpx.push_str(concat!(
+            "<gpx version=\"1.1\" creator=\"path_finder\" ",
+            "xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+        ));
+        gpx.push_str(&format!("  <{}>\n", outer));
+        gpx.push_str(&format!("    <name>{}</name>\n", escape_xml(name)));
+        gpx.push_str(&format!("    <desc>{}</desc>\n", self.summary()));
+
+        let indent = if as_route { "    " } else { "      " };
+        if !as_route {
+            gpx.push_str("    <trkseg>\n");
+        }
+        for node in &self.nodes {
+            gpx.push_str(&format!(
+                "{}<{} lat=\"{}\" lon=\"{}\"/>\n",
+                indent, point, node.lat, node.lon
+            ));
+        }
+        if !as_route {
+            gpx.push_str("    </trkseg>\n");
+        }
+
+        gpx.push_str(&format!("  </{}>\n", outer));
+        gpx.push_str("</gpx>\n");
+        gpx
+    }
+
+    /// A KML 2.2 document with a placemark holding the route as a line clamped to the ground
+    /// The placemark has no geometry when the route has fewer than 2 nodes
+    pub fn to_kml(&self, name: &str) -> String {
+        let mut kml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        kml.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n");
+        kml.push_str("  <Document>\n");
+        kml.push_str("    <Placemark>\n");
+        kml.push_str(&format!("      <name>{}</name>\n", escape_xml(name)));
+        kml.push_str(&format!(
+            "      <description>{}</description>\n",
+            self.summary()
+        ));
+
+        if self.nodes.len() >= 2 {
+            let coordinates: Vec<String> = self
+                .nodes
+                .iter()
+                .map(|node| format!("{},{}", node.lon, node.lat))
+                .collect();
+            kml.push_str("      <LineString>\n");
+            kml.push_str("        <tessellate>1</tessellate>\n");
+            kml.push_str(&format!(
+                "        <coordinates>{}</coordinates>\n",
+                coordinates.join(" ")
+            ));
+            kml.push_str("      </LineString>\n");
+        }
+
+        kml.push_str("    </Placemark>\n");
+        kml.push_str("  </Document>\n");
+        kml.push_str("</kml>\n");
+        kml
+    }
+
+    /// Length and travel time for the description of GPX and KML
+    fn summary(&self) -> String {
+        format!("{:.0} m, {:.0} s", self.distance, self.duration)
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Graph;
+
+    fn road() -> Graph {
+        let mut graph = Graph::new();
+        graph.add_edge_two_way((1, 50.0, 0.0), (2, 50.0, 0.01));
+        graph.add_edge_two_way((2, 50.0, 0.01), (3, 50.01, 0.01));
+        graph
+    }
+
+    #[test]
+    fn test_parse_export_format() {
+        assert_eq!(
+            "gpx_route".parse::<ExportFormat>(),
+            Ok(ExportFormat::GpxRoute)
+        );
+        assert_eq!(
+            "shp".parse::<ExportFormat>(),
+            Err(PathError::UnknownExportFormat("shp".to_string()))
+        );
+        for format in [
+            ExportFormat::GeoJson,
+            ExportFormat::Gpx,
+            ExportFormat::GpxRoute,
+            ExportFormat::Kml,
+        ] {
+            assert_eq!(format.to_string().parse::<ExportFormat>(), Ok(format));
+        }
+        assert_eq!(ExportFormat::from_extension("KML"), Some(ExportFormat::Kml));
+        assert_eq!(
+            ExportFormat::from_extension("json"),
+            Some(ExportFormat::GeoJson)
+        );
+        assert_eq!(ExportFormat::from_extension("csv"), None);
+    }
+
+    #[test]
+    fn test_route_geojson() {
+        let route = road().find_shortest_path(1, 3);
+
+        let feature = route.to_geojson();
+
+        assert_eq!(feature["type"], "Feature");
+        assert_eq!(feature["geometry"]["type"], "LineString");
+        assert_eq!(
+            feature["geometry"]["coordinates"],
+            json!([[0.0, 50.0], [0.01, 50.0], [0.01, 50.01]])
+        );
+        assert_eq!(feature["properties"]["distance"], json!(route.distance));
+        assert_eq!(feature["properties"]["nodes"], json!([1, 2, 3]));
+
+        let exported: Value =
+            serde_json::from_str(&route.export(ExportFormat::GeoJson, "To work")).unwrap();
+        assert_eq!(exported["properties"]["name"], "To work");
+        assert!(road().find_shortest_path(1, 1).to_geojson()["geometry"].is_null());
+    }
+
+    #[test]
+    fn test_route_gpx() {
+        let route = road().find_shortest_path(1, 3);
+
+        let track = route.to_gpx("Fish & chips", false);
+        assert!(track.starts_with("<?xml"));
+        assert!(track.contains("<name>Fish &amp; chips</name>"));
+        assert_eq!(track.matches("<trkpt ").count(), 3);
+        assert!(track.contains("<trkpt lat=\"50.01\" lon=\"0.01\"/>"));
+        assert!(track.contains("<trkseg>") && !track.contains("<rte>"));
+
+        let gpx_route = route.export(ExportFormat::GpxRoute, "Fish & chips");
+        assert_eq!(gpx_route.matches("<rtept ").count(), 3);
+        assert!(!gpx_route.contains("<trk>"));
+    }
+
+    #[test]
+    fn test_route_kml() {
+        let route = road().find_shortest_path(1, 3);
+
+        let kml = route.to_kml("<home>");
+
+        assert!(kml.contains("<name>&lt;home&gt;</name>"));
+        assert!(kml.contains("<coordinates>0,50 0.01,50 0.01,50.01</coordinates>"));
+        assert!(!road()
+            .find_shortest_path(3, 42)
+            .to_kml("")
+            .contains("<LineString>"));
+    }
+}
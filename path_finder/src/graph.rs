@@ -1,42 +1,242 @@
 use std::{
     cmp::Reverse,
-    collections::{BinaryHeap, HashMap},
+    collections::{hash_map::Entry, BinaryHeap, HashMap},
     error::Error,
     fs::File,
     io::BufReader,
 };
 
 use ordered_float::OrderedFloat;
-use rstar::RTree;
+use rstar::{PointDistance, RTree};
 use serde::{Deserialize, Serialize};
 
-use crate::{osm_data::OSMData, r_tree::NodePoint};
+use crate::{
+    algorithm::Algorithm,
+    error::PathError,
+    metric::Metric,
+    osm_data::OSMData,
+    profile::{Oneway, Profile},
+    r_tree::{chord_2_to_metres, metres_to_chord_2, to_position, NodePoint, SegmentLine},
+    restriction::TurnRestriction,
+    search::{SearchOptions, SearchResult},
+    speed::FALLBACK_SPEED_KMH,
+    trace::{SearchTrace, Tracer},
+    turn_cost::TurnCostModel,
+    way_filter::WayFilter,
+};
+
+mod a_star;
+mod area;
+mod bellman_ford;
+mod bidirectional;
+mod contraction;
+mod instructions;
+mod isochrone;
+mod k_shortest;
+mod landmarks;
+mod matrix;
+mod multi_stop;
+mod route;
+mod snap;
+mod tour;
+mod turn_aware;
+
+pub use area::{NetworkEdge, SubNetwork};
+pub use contraction::ContractionHierarchy;
+pub use instructions::{Directions, Instruction, Maneuver};
+pub use isochrone::{Isochrone, IsochroneBand, ReachableNode};
+pub use landmarks::{LandmarkStrategy, Landmarks};
+pub use matrix::DistanceMatrix;
+pub use multi_stop::{Leg, MultiStopRoute};
+pub use route::{Route, RouteEdge, RouteNode};
+pub use snap::{EdgeSnap, PointPath};
+pub use tour::Tour;
+
+/// Node id mapped to its latitude, longitude and outgoing edges
+type AdjacencyList = HashMap<u64, (f64, f64, Vec<Edge>)>;
+
+/// Node id mapped to its incoming edges as `(from, index into the outgoing edges of from)`
+type ReverseAdjacencyList = HashMap<u64, Vec<(u64, usize)>>;
+
+/// A directed edge to a neighbouring node
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Edge {
+    pub to: u64,
+    /// Cost used by the searches, the length adjusted by the profile the graph was built with
+    pub weight: f32,
+    /// Length of the edge in `km`
+    pub distance: f32,
+    /// Estimated travel time in seconds
+    pub duration: f32,
+    /// The OSM way the edge was built from, `None` for edges added by hand
+    pub way_id: Option<u64>,
+}
+
+impl Edge {
+    /// Edge weighted by its length, travelled at the fallback speed
+    fn new(to: u64, distance: f32) -> Self {
+        Edge {
+            to,
+            weight: distance,
+            distance,
+            duration: distance / FALLBACK_SPEED_KMH * 3600.0,
+            way_id: None,
+        }
+    }
+
+    /// Cost of the edge for the metric being minimised
+    pub fn cost(&self, metric: Metric) -> f32 {
+        match metric {
+            Metric::Shortest => self.weight,
+            Metric::Fastest => self.duration,
+        }
+    }
+}
+
+/// What the graph keeps of the OSM way an edge was built from, for describing routes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WayInfo {
+    pub name: Option<String>,
+    /// Road number, like `A27`
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    /// The `highway` tag, like `primary` or `residential`
+    pub highway: Option<String>,
+    pub roundabout: bool,
+}
+
+impl WayInfo {
+    pub(crate) fn from_tags(tags: &HashMap<String, String>) -> Self {
+        WayInfo {
+            name: tags.get("name").cloned(),
+            reference: tags.get("ref").cloned(),
+            highway: tags.get("highway").cloned(),
+            roundabout: tags
+                .get("junction")
+                .is_some_and(|junction| junction == "roundabout" || junction == "circular"),
+        }
+    }
+
+    /// Name and road number as shown to people, like `Chichester Bypass (A27)`
+    pub fn road_name(&self) -> Option<String> {
+        match (&self.name, &self.reference) {
+            (Some(name), Some(reference)) => Some(format!("{} ({})", name, reference)),
+            (Some(name), None) => Some(name.clone()),
+            (None, Some(reference)) => Some(reference.clone()),
+            (None, None) => None,
+        }
+    }
+}
 
 /// This represents the weighted graph
 /// Where each will have a id of the node as the key
 /// And the tuple with another node id and the calculated distance
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Graph {
-    adj_list: HashMap<u64, (f64, f64, Vec<(u64, f32)>)>,
+    adj_list: AdjacencyList,
+    /// Same edges as `adj_list` followed backwards, so one way streets stay one way when searching
+    /// from the end
+    rev_adj_list: ReverseAdjacencyList,
     rtree: RTree<NodePoint>,
+    /// Every pair of neighbouring nodes once, for snapping points onto the roads between the nodes
+    segment_rtree: RTree<SegmentLine>,
+    /// The OSM ways the edges were built from, keyed by way id
+    ways: HashMap<u64, WayInfo>,
+    /// Turn restrictions of the profile, keyed by their via node
+    restrictions: HashMap<u64, Vec<TurnRestriction>>,
+    /// Turn penalties of the profile, only applied when a search asks for them
+    turn_costs: TurnCostModel,
+    /// Name of the profile the graph was built for, empty when it was built edge by edge
+    profile: String,
+    /// Built on demand, dropped whenever an edge is added
+    contraction_hierarchy: Option<ContractionHierarchy>,
+    /// Lower bound tables for `ALT`, also dropped whenever an edge is added
+    landmarks: Option<Landmarks>,
+    /// Highest `km` per unit of weight over all edges, lets heuristics turn a distance into a lower
+    /// bound on weight
+    max_km_per_weight: f32,
+    /// Highest speed over all edges in `km/h`
+    max_speed: f32,
 }
 
 impl Graph {
     pub fn new() -> Self {
         Graph {
             rtree: RTree::new(),
+            segment_rtree: RTree::new(),
             ..Default::default()
         }
     }
 
     pub fn nearest_neighbor(&self, lat: f64, lon: f64) -> Option<u64> {
-        self.rtree.nearest_neighbor(&[lat, lon]).map(|node| node.id)
+        self.rtree
+            .nearest_neighbor(&to_position(lat, lon))
+            .map(|node| node.id)
+    }
+
+    /// Up to `k` nodes nearest to the point with their great circle distances in metres, nearest
+    /// first
+    pub fn k_nearest_nodes(&self, lat: f64, lon: f64, k: usize) -> Vec<(u64, f64)> {
+        self.rtree
+            .nearest_neighbor_iter_with_distance_2(&to_position(lat, lon))
+            .take(k)
+            .map(|(node, distance_2)| (node.id, chord_2_to_metres(distance_2)))
+            .collect()
+    }
+
+    /// Every node within `radius` metres of the point with its great circle distance in metres,
+    /// nearest first
+    pub fn nodes_within_radius(&self, lat: f64, lon: f64, radius: f64) -> Vec<(u64, f64)> {
+        let position = to_position(lat, lon);
+        let mut nodes: Vec<(u64, f64)> = self
+            .rtree
+            .locate_within_distance(position, metres_to_chord_2(radius))
+            .map(|node| (node.id, chord_2_to_metres(node.distance_2(&position))))
+            .collect();
+        nodes.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        nodes
+    }
+
+    /// Snaps each `(lat, lon)` point to its nearest node
+    /// Fails with the index of the first point when the graph is empty
+    pub fn snap_points(&self, points: &[(f64, f64)]) -> Result<Vec<u64>, PathError> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(index, &(lat, lon))| {
+                self.nearest_neighbor(lat, lon)
+                    .ok_or(PathError::NoNearbyNode(index))
+            })
+            .collect()
     }
 
     pub fn contains_node_id(&self, id: u64) -> bool {
         self.adj_list.contains_key(&id)
     }
 
+    pub fn profile_name(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn turn_costs(&self) -> &TurnCostModel {
+        &self.turn_costs
+    }
+
+    /// Replaces the turn penalties taken from the profile
+    pub fn set_turn_costs(&mut self, turn_costs: TurnCostModel) {
+        self.turn_costs = turn_costs;
+    }
+
+    /// Name, road number and class of an OSM way edges were built from
+    pub fn way(&self, id: u64) -> Option<&WayInfo> {
+        self.ways.get(&id)
+    }
+
+    /// Returns the latitude and longitude of the node
+    pub(crate) fn coordinates(&self, id: u64) -> Option<(f64, f64)> {
+        self.adj_list.get(&id).map(|&(lat, lon, _)| (lat, lon))
+    }
+
     pub fn to_json(&self) -> String {
         serde_json::to_string_pretty(&self).unwrap()
     }
@@ -45,29 +245,61 @@ impl Graph {
     /// Point here is a tuple with the node id, lat and long
     pub fn add_edge_one_way(&mut self, from: (u64, f64, f64), to: (u64, f64, f64)) {
         let distance_km = Graph::calculate_distance((from.1, from.2), (to.1, to.2));
-        self.add_edge(from, to, distance_km);
+        self.add_edge(from, to, Edge::new(to.0, distance_km));
     }
 
     /// This takes two points and adds edge for from <-> to
     /// Point here is a tuple with the node id, lat and long
     pub fn add_edge_two_way(&mut self, from: (u64, f64, f64), to: (u64, f64, f64)) {
         let distance_km = Graph::calculate_distance((from.1, from.2), (to.1, to.2));
-        self.add_edge(from, to, distance_km);
-        self.add_edge(to, from, distance_km);
+        self.add_edge(from, to, Edge::new(to.0, distance_km));
+        self.add_edge(to, from, Edge::new(from.0, distance_km));
+    }
+
+    /// Builds the graph from the routable ways only, see `WayFilter::default`
+    pub(crate) fn from_osm_data(osm_data: &OSMData) -> Result<Self, Box<dyn Error>> {
+        Self::from_osm_data_with_profile(osm_data, &Profile::default())
     }
 
-    pub(crate) fn from_osm_data(osm_data: OSMData) -> Result<Self, Box<dyn Error>> {
+    /// Builds the graph from the ways accepted by the filter, everything else (buildings,
+    /// landuse...) is skipped
+    pub(crate) fn from_osm_data_with_filter(
+        osm_data: &OSMData,
+        filter: &WayFilter,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_osm_data_with_profile(
+            osm_data,
+            &Profile::default().with_way_filter(filter.clone()),
+        )
+    }
+
+    /// Builds the graph for one kind of traffic
+    /// The profile decides which ways are used, whether they are one way and how much each edge
+    /// costs
+    pub(crate) fn from_osm_data_with_profile(
+        osm_data: &OSMData,
+        profile: &Profile,
+    ) -> Result<Self, Box<dyn Error>> {
         let mut graph = Graph::new();
-        let mut valid_nodes: HashMap<u64, (f64, f64)> = HashMap::new();
+        graph.turn_costs = profile.turn_costs;
+        graph.profile = profile.name.clone();
         let mut node_map: HashMap<u64, (f64, f64)> = HashMap::new();
 
         for node in &osm_data.nodes {
             node_map.insert(node.id, (node.lat, node.lon));
         }
 
-        for way in &osm_data.ways {
-            let is_oneway = way.tags.get("oneway").map_or(false, |v| v == "yes");
+        for way in osm_data
+            .ways
+            .iter()
+            .filter(|way| profile.is_accessible(&way.tags))
+        {
+            let oneway = profile.oneway(&way.tags);
+            if oneway == Oneway::Closed {
+                continue;
+            }
 
+            graph.ways.insert(way.id, WayInfo::from_tags(&way.tags));
             for pair in way.nodes.windows(2) {
                 let from_id = pair[0];
                 let to_id = pair[1];
@@ -75,27 +307,39 @@ impl Graph {
                 if let (Some(&from_coords), Some(&to_coords)) =
                     (node_map.get(&from_id), node_map.get(&to_id))
                 {
-                    valid_nodes.insert(from_id, from_coords);
-                    valid_nodes.insert(to_id, to_coords);
-
-                    if is_oneway {
-                        graph.add_edge_one_way(
-                            (from_id, from_coords.0, from_coords.1),
-                            (to_id, to_coords.0, to_coords.1),
-                        );
-                    } else {
-                        graph.add_edge_two_way(
-                            (from_id, from_coords.0, from_coords.1),
-                            (to_id, to_coords.0, to_coords.1),
-                        );
+                    let from = (from_id, from_coords.0, from_coords.1);
+                    let to = (to_id, to_coords.0, to_coords.1);
+                    let distance_km = Graph::calculate_distance(from_coords, to_coords);
+                    let edge = |to: u64| Edge {
+                        to,
+                        weight: profile.edge_cost(&way.tags, distance_km),
+                        distance: distance_km,
+                        duration: distance_km / profile.speed(&way.tags) * 3600.0,
+                        way_id: Some(way.id),
+                    };
+
+                    if oneway != Oneway::Backward {
+                        graph.add_edge(from, to, edge(to_id));
+                    }
+                    if oneway != Oneway::Forward {
+                        graph.add_edge(to, from, edge(from_id));
                     }
                 }
             }
         }
 
-        for (&node_id, &(lat, lon)) in &valid_nodes {
-            let node_point = NodePoint { id: node_id, lat, lon };
-            graph.rtree.insert(node_point);
+        if let Some(mode) = &profile.restriction_mode {
+            for restriction in osm_data
+                .relations
+                .iter()
+                .filter_map(|relation| TurnRestriction::from_relation(relation, mode))
+            {
+                graph
+                    .restrictions
+                    .entry(restriction.via_node)
+                    .or_default()
+                    .push(restriction);
+            }
         }
 
         Ok(graph)
@@ -104,15 +348,127 @@ impl Graph {
     /// This function uses the path given as argument, to construct the graph using json file
     /// Identifies if it is one way or two way using the tag in the way
     pub fn from_json_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        Self::from_osm_data(&Self::read_osm_data(path)?)
+    }
+
+    /// Same as `from_json_file` but only keeps the ways accepted by the filter
+    pub fn from_json_file_with_filter(
+        path: &str,
+        filter: &WayFilter,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_osm_data_with_filter(&Self::read_osm_data(path)?, filter)
+    }
+
+    /// Same as `from_json_file` but builds the graph for the given profile
+    pub fn from_json_file_with_profile(
+        path: &str,
+        profile: &Profile,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_osm_data_with_profile(&Self::read_osm_data(path)?, profile)
+    }
+
+    fn read_osm_data(path: &str) -> Result<OSMData, Box<dyn Error>> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
 
-        let osm_data: OSMData = serde_json::from_reader(reader)?;
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Finds the path between two nodes with the given algorithm
+    pub fn find_path(
+        &self,
+        start: u64,
+        end: u64,
+        algorithm: Algorithm,
+    ) -> Result<Route, PathError> {
+        let options = SearchOptions {
+            algorithm,
+            ..Default::default()
+        };
+        self.search(start, end, &options).map(|result| result.route)
+    }
+
+    /// Runs a path query as described by the options
+    /// The exploration order is included in the result when `options.trace` is set
+    pub fn search(
+        &self,
+        start: u64,
+        end: u64,
+        options: &SearchOptions,
+    ) -> Result<SearchResult, PathError> {
+        let mut trace = options.trace.then(SearchTrace::default);
+        let tracer = Tracer::new(self, trace.as_mut());
+
+        let metric = options.metric;
+
+        // the node based searches can't tell which way a node was entered from,
+        // so restricted graphs and turn costs are searched edge by edge by the algorithms that can
+        // be, the others fail rather than return a path that breaks a restriction
+        let path = if self.needs_turn_aware_search(options) {
+            match options.algorithm {
+                Algorithm::Dijkstra => self.turn_aware_search(start, end, options, |_| 0.0, tracer),
+                Algorithm::AStar => self.turn_aware_search(
+                    start,
+                    end,
+                    options,
+                    self.distance_heuristic(end, metric),
+                    tracer,
+                ),
+                Algorithm::Alt => {
+                    let landmarks = self.landmarks_for(metric)?;
+                    self.turn_aware_search(
+                        start,
+                        end,
+                        options,
+                        |node| landmarks.lower_bound(node, end),
+                        tracer,
+                    )
+                }
+                algorithm => return Err(PathError::UnsupportedWithRestrictions(algorithm)),
+            }
+        } else {
+            match options.algorithm {
+                Algorithm::Dijkstra => self.dijkstra(start, end, metric, tracer),
+                Algorithm::AStar => self.astar(start, end, metric, tracer),
+                Algorithm::BellmanFord => {
+                    self.bellman_ford(start, end, metric, |_, _, weight| weight, tracer)?
+                }
+                Algorithm::Bidirectional => self.bidirectional_dijkstra(start, end, metric, tracer),
+                Algorithm::Alt => self.alt(start, end, metric, tracer)?,
+                Algorithm::ContractionHierarchy => {
+                    self.contraction_hierarchy_search(start, end, metric, tracer)?
+                }
+            }
+        };
+
+        let mut route = self.to_route(&path, metric);
+        if options.turn_costs {
+            self.add_turn_penalties(&mut route);
+        }
+        route.set_polyline(options.polyline)?;
+        Ok(SearchResult { route, trace })
+    }
 
-        Self::from_osm_data(osm_data)
+    /// Whether a search with the options has to be edge based, for turn costs or for the
+    /// restrictions of the graph
+    pub(super) fn needs_turn_aware_search(&self, options: &SearchOptions) -> bool {
+        options.turn_costs || (!options.ignore_turn_restrictions && !self.restrictions.is_empty())
     }
 
-    pub fn find_shortest_path(&self, start: u64, end: u64) -> Vec<(u64, f64, f64)> {
+    pub fn find_shortest_path(&self, start: u64, end: u64) -> Route {
+        self.to_route(
+            &self.dijkstra(start, end, Metric::Shortest, Tracer::new(self, None)),
+            Metric::Shortest,
+        )
+    }
+
+    fn dijkstra(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        mut tracer: Tracer,
+    ) -> Vec<(u64, f64, f64)> {
         let mut distances: HashMap<u64, f32> = HashMap::new();
         let mut predecessors: HashMap<u64, u64> = HashMap::new();
         let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>> = BinaryHeap::new();
@@ -127,25 +483,44 @@ impl Graph {
         heap.push(Reverse((OrderedFloat(0.0), start)));
 
         while let Some(Reverse((cost, node))) = heap.pop() {
-            if node == end {
-                break;
+            if cost > OrderedFloat(*distances.get(&node).unwrap_or(&f32::MAX)) {
+                continue;
             }
 
-            if cost > OrderedFloat(distances[&node]) {
-                continue;
+            tracer.settled(node, cost.into_inner());
+            if node == end {
+                break;
             }
 
-            if let Some((_, _, neighbours)) = self.adj_list.get(&node) {
-                for &(neighbour, weight) in neighbours {
-                    let new_cost = cost + weight;
+            if let Some((_, _, edges)) = self.adj_list.get(&node) {
+                for edge in edges {
+                    let neighbour = edge.to;
+                    let new_cost = cost + edge.cost(metric);
                     if new_cost < OrderedFloat(*distances.get(&neighbour).unwrap_or(&f32::MAX)) {
                         distances.insert(neighbour, new_cost.into_inner());
                         predecessors.insert(neighbour, node);
+                        tracer.relaxed(node, neighbour, new_cost.into_inner());
                         heap.push(Reverse((new_cost, neighbour)));
                     }
                 }
             }
         }
+
+        self.build_path(start, end, &predecessors)
+    }
+
+    /// Walks the predecessors back from `end` to `start` and returns the path with coordinates
+    /// Returns an empty path when `end` was never reached
+    fn build_path(
+        &self,
+        start: u64,
+        end: u64,
+        predecessors: &HashMap<u64, u64>,
+    ) -> Vec<(u64, f64, f64)> {
+        if start != end && !predecessors.contains_key(&end) {
+            return Vec::new();
+        }
+
         let mut path = Vec::new();
         let mut current = end;
 
@@ -164,17 +539,133 @@ impl Graph {
         path
     }
 
-    /// Helper function add edge to the list with weight
-    fn add_edge(&mut self, from: (u64, f64, f64), to: (u64, f64, f64), weight: f32) {
+    /// Returns a lower bound on the cost of covering `distance_km`, as used by the `A*` heuristics
+    fn lower_bound(&self, distance_km: f32, metric: Metric) -> f32 {
+        let km_per_cost = match metric {
+            Metric::Shortest => self.max_km_per_weight,
+            Metric::Fastest => self.max_speed / 3600.0,
+        };
+
+        if km_per_cost > 0.0 {
+            distance_km / km_per_cost
+        } else {
+            0.0
+        }
+    }
+
+    /// The reverse edge is recorded as well, new nodes and segments are indexed for snapping
+    fn add_edge(&mut self, from: (u64, f64, f64), to: (u64, f64, f64), edge: Edge) {
+        self.contraction_hierarchy = None;
+        self.landmarks = None;
+        if edge.weight > 0.0 {
+            self.max_km_per_weight = self.max_km_per_weight.max(edge.distance / edge.weight);
+        }
+        if edge.duration > 0.0 {
+            self.max_speed = self.max_speed.max(edge.distance / edge.duration * 3600.0);
+        }
+
+        for (id, lat, lon) in [from, to] {
+            if let Entry::Vacant(entry) = self.adj_list.entry(id) {
+                entry.insert((lat, lon, Vec::new()));
+                self.rtree.insert(NodePoint { id, lat, lon });
+            }
+        }
+
+        // a node keeps the coordinates it was first added with
+        let (from_lat, from_lon, from_edges) = &self.adj_list[&from.0];
+        let (to_lat, to_lon, to_edges) = &self.adj_list[&to.0];
+        let connected = from_edges.iter().any(|edge| edge.to == to.0)
+            || to_edges.iter().any(|edge| edge.to == from.0);
+        if from.0 != to.0 && !connected {
+            self.segment_rtree.insert(SegmentLine {
+                from: from.0,
+                to: to.0,
+                from_point: [*from_lat, *from_lon],
+                to_point: [*to_lat, *to_lon],
+            });
+        }
+
+        let edges = &mut self.adj_list.get_mut(&from.0).unwrap().2;
+        edges.push(edge);
+        let index = edges.len() - 1;
+
+        self.rev_adj_list
+            .entry(to.0)
+            .or_default()
+            .push((from.0, index));
+    }
+
+    /// Costs of the cheapest paths from `source` to every node it reaches with their predecessors,
+    /// or with `reverse` from every node that reaches `source`
+    fn costs_from(
+        &self,
+        source: u64,
+        metric: Metric,
+        reverse: bool,
+    ) -> (HashMap<u64, f32>, HashMap<u64, u64>) {
+        let mut costs: HashMap<u64, f32> = HashMap::new();
+        let mut predecessors: HashMap<u64, u64> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>> = BinaryHeap::new();
+
+        costs.insert(source, 0.0);
+        heap.push(Reverse((OrderedFloat(0.0), source)));
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            let cost = cost.into_inner();
+            if cost > costs[&node] {
+                continue;
+            }
+
+            let neighbours = if reverse {
+                self.incoming_edges(node, metric)
+            } else {
+                self.adj_list
+                    .get(&node)
+                    .map_or_else(Vec::new, |(_, _, edges)| {
+                        edges
+                            .iter()
+                            .map(|edge| (edge.to, edge.cost(metric)))
+                            .collect()
+                    })
+            };
+
+            for (neighbour, edge_cost) in neighbours {
+                let new_cost = cost + edge_cost;
+                if new_cost < *costs.get(&neighbour).unwrap_or(&f32::MAX) {
+                    costs.insert(neighbour, new_cost);
+                    predecessors.insert(neighbour, node);
+                    heap.push(Reverse((OrderedFloat(new_cost), neighbour)));
+                }
+            }
+        }
+
+        (costs, predecessors)
+    }
+
+    /// The edge a search minimising the metric takes between two neighbours
+    fn cheapest_edge(&self, from: u64, to: u64, metric: Metric) -> Option<&Edge> {
         self.adj_list
-            .entry(from.0)
-            .or_insert_with(|| (from.1, from.2, Vec::new()))
+            .get(&from)?
             .2
-            .push((to.0, weight));
+            .iter()
+            .filter(|edge| edge.to == to)
+            .min_by_key(|edge| OrderedFloat(edge.cost(metric)))
+    }
 
-        self.adj_list
-            .entry(to.0)
-            .or_insert_with(|| (to.1, to.2, Vec::new()));
+    /// Total `km` and seconds of a path, following the edges a search minimising the metric takes
+    fn path_totals(&self, path: &[(u64, f64, f64)], metric: Metric) -> (f32, f32) {
+        path.windows(2)
+            .filter_map(|pair| self.cheapest_edge(pair[0].0, pair[1].0, metric))
+            .fold((0.0, 0.0), |(distance, duration), edge| {
+                (distance + edge.distance, duration + edge.duration)
+            })
+    }
+
+    fn with_coordinates(&self, nodes: &[u64]) -> Vec<(u64, f64, f64)> {
+        nodes
+            .iter()
+            .filter_map(|&node| self.coordinates(node).map(|(lat, lon)| (node, lat, lon)))
+            .collect()
     }
 
     /// Calculates the distance in `km` using `Harvesine` formula
@@ -200,11 +691,15 @@ impl Graph {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::{
+        osm_data::{Node, Relation, RelationMember, Way},
+        search::SearchOptions,
+    };
 
-    #[test]
-    fn test_shortest_path() {
+    /// Two way graph used across the algorithm tests
+    pub(super) fn sample_graph() -> Graph {
         let mut graph = Graph::new();
         // 5km
         graph.add_edge_two_way((1, 51.5074, 0.1278), (2, 51.5074, 0.20005));
@@ -216,17 +711,11 @@ mod tests {
         graph.add_edge_two_way((4, 51.5074, 0.1278), (5, 51.5074, 0.1712));
         // 1.26km
         graph.add_edge_two_way((2, 51.5074, 0.1278), (5, 51.5074, 0.11008));
-
-        assert_eq!(graph.find_shortest_path(1, 3), vec![(1, 51.5074, 0.1278), (2, 51.5074, 0.20005), (3, 51.5074, 0.27230)]);
-        assert_eq!(graph.find_shortest_path(1, 5), vec![(1, 51.5074, 0.1278), (4, 51.5074, 0.100000), (5, 51.5074, 0.1712)]);
-        assert_eq!(graph.find_shortest_path(3, 5), vec![(3, 51.5074, 0.27230), (2, 51.5074, 0.20005), (5, 51.5074, 0.1712)]);
-        assert_eq!(graph.find_shortest_path(1, 4), vec![(1, 51.5074, 0.1278), (4, 51.5074, 0.100000)]);
-        assert_eq!(graph.find_shortest_path(1, 1), vec![(1, 51.5074, 0.1278)]);
-        assert_eq!(graph.find_shortest_path(3, 4), vec![(3, 51.5074, 0.27230), (2, 51.5074, 0.20005), (5, 51.5074, 0.1712), (4, 51.5074, 0.1278)]);
+        graph
     }
 
-    #[test]
-    fn test_shortest_path_no_path() {
+    /// Mostly one way graph where some nodes can not reach the others
+    pub(super) fn one_way_graph() -> Graph {
         let mut graph = Graph::new();
         // 5km
         graph.add_edge_two_way((1, 51.5074, 0.1278), (2, 51.5074, 0.20005));
@@ -238,8 +727,584 @@ mod tests {
         graph.add_edge_one_way((2, 51.5074, 0.1278), (3, 51.5074, 0.27230));
         // 5km
         graph.add_edge_one_way((3, 51.5074, 0.1278), (5, 51.5074, 0.20005));
+        graph
+    }
+
+    /// Builds OSM data with every node placed along a line of latitude, 100m apart
+    pub(super) fn osm_data(node_ids: &[u64], ways: Vec<Way>) -> OSMData {
+        let nodes = node_ids
+            .iter()
+            .map(|&id| Node {
+                id,
+                lat: 50.8365,
+                lon: -0.78 + id as f64 * 0.0014,
+                tags: HashMap::new(),
+            })
+            .collect();
+        OSMData {
+            nodes,
+            ways,
+            relations: Vec::new(),
+        }
+    }
+
+    /// Builds OSM data from nodes given as `(id, lat, lon)`
+    pub(super) fn osm_data_at(
+        nodes: &[(u64, f64, f64)],
+        ways: Vec<Way>,
+        relations: Vec<Relation>,
+    ) -> OSMData {
+        let nodes = nodes
+            .iter()
+            .map(|&(id, lat, lon)| Node {
+                id,
+                lat,
+                lon,
+                tags: HashMap::new(),
+            })
+            .collect();
+        OSMData {
+            nodes,
+            ways,
+            relations,
+        }
+    }
+
+    /// Restriction relation with a single from way, via node and to way
+    pub(super) fn restriction(id: u64, kind: &str, from: u64, via: u64, to: u64) -> Relation {
+        let member = |member_type: &str, ref_id: u64, role: &str| RelationMember {
+            _type: member_type.to_string(),
+            ref_id,
+            role: role.to_string(),
+        };
+        Relation {
+            id,
+            members: vec![
+                member("way", from, "from"),
+                member("node", via, "via"),
+                member("way", to, "to"),
+            ],
+            tags: tags(&[("type", "restriction"), ("restriction", kind)]),
+        }
+    }
+
+    /// OSM tags from `(key, value)` pairs
+    pub(crate) fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    pub(super) fn way(id: u64, nodes: &[u64], tags: &[(&str, &str)]) -> Way {
+        Way {
+            id,
+            nodes: nodes.to_vec(),
+            tags: self::tags(tags),
+        }
+    }
+
+    /// Random graph with one way and two way streets scattered around Chichester,
+    /// generated from a small linear congruential generator so runs are repeatable
+    pub(super) fn random_graph(seed: u64, nodes: u64, edges: usize) -> Graph {
+        let mut rng = Lcg(seed);
+        let coords: Vec<(u64, f64, f64)> = (0..nodes)
+            .map(|id| {
+                (
+                    id,
+                    50.82 + rng.next_f64() * 0.03,
+                    -0.80 + rng.next_f64() * 0.05,
+                )
+            })
+            .collect();
+
+        let mut graph = Graph::new();
+        for _ in 0..edges {
+            let from = coords[rng.next_below(nodes) as usize];
+            let to = coords[rng.next_below(nodes) as usize];
+            if from.0 == to.0 {
+                continue;
+            }
+            if rng.next_below(4) == 0 {
+                graph.add_edge_one_way(from, to);
+            } else {
+                graph.add_edge_two_way(from, to);
+            }
+        }
+        graph
+    }
+
+    /// Sums the cheapest edge weight between consecutive nodes of the path
+    pub(super) fn path_cost(graph: &Graph, route: &Route) -> f32 {
+        route
+            .nodes
+            .windows(2)
+            .map(|pair| {
+                graph.adj_list[&pair[0].id]
+                    .2
+                    .iter()
+                    .filter(|edge| edge.to == pair[1].id)
+                    .map(|edge| edge.weight)
+                    .fold(f32::MAX, f32::min)
+            })
+            .sum()
+    }
+
+    pub(super) struct Lcg(pub u64);
+
+    impl Lcg {
+        pub(super) fn next_u64(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+
+        pub(super) fn next_below(&mut self, bound: u64) -> u64 {
+            self.next_u64() % bound
+        }
+
+        pub(super) fn next_f64(&mut self) -> f64 {
+            self.next_u64() as f64 / (1u64 << 31) as f64
+        }
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let graph = sample_graph();
+
+        assert_eq!(
+            graph.find_shortest_path(1, 3).path(),
+            vec![
+                (1, 51.5074, 0.1278),
+                (2, 51.5074, 0.20005),
+                (3, 51.5074, 0.27230)
+            ]
+        );
+        assert_eq!(
+            graph.find_shortest_path(1, 5).path(),
+            vec![
+                (1, 51.5074, 0.1278),
+                (4, 51.5074, 0.100000),
+                (5, 51.5074, 0.1712)
+            ]
+        );
+        assert_eq!(
+            graph.find_shortest_path(3, 5).path(),
+            vec![
+                (3, 51.5074, 0.27230),
+                (2, 51.5074, 0.20005),
+                (5, 51.5074, 0.1712)
+            ]
+        );
+        assert_eq!(
+            graph.find_shortest_path(1, 4).path(),
+            vec![(1, 51.5074, 0.1278), (4, 51.5074, 0.100000)]
+        );
+        assert_eq!(
+            graph.find_shortest_path(1, 1).path(),
+            vec![(1, 51.5074, 0.1278)]
+        );
+        assert_eq!(
+            graph.find_shortest_path(3, 4).path(),
+            vec![
+                (3, 51.5074, 0.27230),
+                (2, 51.5074, 0.20005),
+                (5, 51.5074, 0.1712),
+                (4, 51.5074, 0.100000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_no_path() {
+        let graph = one_way_graph();
+
+        assert_eq!(
+            graph.find_shortest_path(1, 3).path(),
+            vec![
+                (1, 51.5074, 0.1278),
+                (4, 51.5074, 0.20005),
+                (3, 51.5074, 0.11008)
+            ]
+        );
+        assert_eq!(
+            graph.find_shortest_path(3, 1).path(),
+            Vec::<(u64, f64, f64)>::new()
+        );
+    }
+
+    /// Haversine in metres, in `f64` so it can be compared to the tree down to the millimetre
+    fn haversine_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+        let a = ((lat2 - lat1).to_radians() / 2.0).sin().powi(2)
+            + lat1.to_radians().cos()
+                * lat2.to_radians().cos()
+                * ((lon2 - lon1).to_radians() / 2.0).sin().powi(2);
+        2.0 * 6_371_000.0 * a.sqrt().asin()
+    }
+
+    /// Every node of the graph with its distance to the point, nearest first
+    fn brute_force_nearest(graph: &Graph, lat: f64, lon: f64) -> Vec<(u64, f64)> {
+        let mut nodes: Vec<(u64, f64)> = graph
+            .adj_list
+            .iter()
+            .map(|(&id, &(node_lat, node_lon, _))| (id, haversine_m(lat, lon, node_lat, node_lon)))
+            .collect();
+        nodes.sort_by(|a, b| a.1.total_cmp(&b.1));
+        nodes
+    }
+
+    #[test]
+    fn test_nearest_neighbor_scales_longitude() {
+        let mut graph = Graph::new();
+        // 0.008 degrees of latitude is 890m, 0.01 degrees of longitude only 703m at this latitude
+        graph.add_edge_two_way((1, 50.808, -0.78), (2, 50.8, -0.77));
+
+        assert_eq!(graph.nearest_neighbor(50.8, -0.78), Some(2));
+    }
+
+    #[test]
+    fn test_k_nearest_nodes_matches_haversine() {
+        let graph = random_graph(11, 300, 600);
+
+        for (lat, lon) in [(50.83, -0.78), (50.82, -0.80), (50.85, -0.75), (50.9, -0.7)] {
+            let expected = brute_force_nearest(&graph, lat, lon);
+
+            let nearest = graph.k_nearest_nodes(lat, lon, 10);
+
+            assert_eq!(nearest.len(), 10);
+            for ((id, distance), (expected_id, expected_distance)) in nearest.iter().zip(&expected)
+            {
+                assert_eq!(id, expected_id);
+                assert!((distance - expected_distance).abs() < 1e-3);
+            }
+            assert_eq!(graph.nearest_neighbor(lat, lon), Some(expected[0].0));
+        }
+        assert_eq!(
+            graph.k_nearest_nodes(50.83, -0.78, 1000).len(),
+            graph.adj_list.len()
+        );
+        assert!(Graph::new().k_nearest_nodes(50.83, -0.78, 3).is_empty());
+    }
+
+    #[test]
+    fn test_nodes_within_radius_matches_haversine() {
+        let graph = random_graph(12, 300, 600);
+
+        for radius in [0.0, 150.0, 400.0, 1000.0] {
+            let (lat, lon) = (50.835, -0.775);
+            let expected: Vec<(u64, f64)> = brute_force_nearest(&graph, lat, lon)
+                .into_iter()
+                .filter(|node| node.1 <= radius)
+                .collect();
+
+            let within = graph.nodes_within_radius(lat, lon, radius);
+
+            assert_eq!(
+                within.iter().map(|node| node.0).collect::<Vec<_>>(),
+                expected.iter().map(|node| node.0).collect::<Vec<_>>()
+            );
+            assert!(within.iter().all(|node| node.1 <= radius));
+        }
+    }
+
+    #[test]
+    fn test_from_osm_data_skips_non_routable_ways() {
+        let data = osm_data(
+            &[1, 2, 3, 4, 5],
+            vec![
+                way(10, &[1, 2, 3], &[("highway", "residential")]),
+                way(11, &[3, 4], &[("building", "yes")]),
+                way(12, &[4, 5, 1], &[("landuse", "grass")]),
+            ],
+        );
+
+        let graph = Graph::from_osm_data(&data).unwrap();
+
+        assert!(graph.contains_node_id(1) && graph.contains_node_id(3));
+        assert!(!graph.contains_node_id(4) && !graph.contains_node_id(5));
+        assert_eq!(graph.rtree.size(), 3);
+    }
+
+    #[test]
+    fn test_from_osm_data_with_filter() {
+        let data = osm_data(
+            &[1, 2, 3],
+            vec![
+                way(10, &[1, 2], &[("highway", "footway")]),
+                way(11, &[2, 3], &[("highway", "primary")]),
+            ],
+        );
+
+        let graph = Graph::from_osm_data_with_filter(&data, &WayFilter::new(["primary"])).unwrap();
+
+        assert!(!graph.contains_node_id(1));
+        assert_eq!(graph.find_shortest_path(2, 3).nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_from_osm_data_with_profile() {
+        let data = osm_data(
+            &[1, 2, 3, 4],
+            vec![
+                way(
+                    10,
+                    &[1, 2, 3],
+                    &[("highway", "residential"), ("oneway", "yes")],
+                ),
+                way(11, &[3, 4], &[("highway", "footway")]),
+            ],
+        );
+
+        let car = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        let foot = Graph::from_osm_data_with_profile(&data, &Profile::foot()).unwrap();
+
+        assert!(!car.contains_node_id(4));
+        assert_eq!(
+            car.find_shortest_path(3, 1).path(),
+            Vec::<(u64, f64, f64)>::new()
+        );
+        assert_eq!(foot.find_shortest_path(4, 1).node_ids(), vec![4, 3, 2, 1]);
+    }
+
+    /// Node ids of the path, or an empty list when there is none
+    fn path_ids(graph: &Graph, start: u64, end: u64) -> Vec<u64> {
+        graph.find_shortest_path(start, end).node_ids()
+    }
+
+    #[test]
+    fn test_oneway_forward() {
+        for value in ["yes", "1", "true"] {
+            let data = osm_data(
+                &[1, 2, 3],
+                vec![way(
+                    10,
+                    &[1, 2, 3],
+                    &[("highway", "residential"), ("oneway", value)],
+                )],
+            );
+            let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+            assert_eq!(path_ids(&graph, 1, 3), vec![1, 2, 3], "oneway={}", value);
+            assert_eq!(
+                path_ids(&graph, 3, 1),
+                Vec::<u64>::new(),
+                "oneway={}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_oneway_backward() {
+        let data = osm_data(
+            &[1, 2, 3],
+            vec![way(
+                10,
+                &[1, 2, 3],
+                &[("highway", "residential"), ("oneway", "-1")],
+            )],
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        assert_eq!(path_ids(&graph, 3, 1), vec![3, 2, 1]);
+        assert_eq!(path_ids(&graph, 1, 3), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_oneway_roundabout_and_motorway() {
+        let data = osm_data(
+            &[1, 2, 3, 4, 5],
+            vec![
+                way(
+                    10,
+                    &[1, 2, 3, 1],
+                    &[("highway", "primary"), ("junction", "roundabout")],
+                ),
+                way(11, &[4, 5], &[("highway", "motorway")]),
+            ],
+        );
+        let car = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        let foot = Graph::from_osm_data_with_profile(&data, &Profile::foot()).unwrap();
+
+        // going straight from 3 to 2 would be against the flow of the roundabout
+        assert_eq!(path_ids(&car, 1, 3), vec![1, 2, 3]);
+        assert_eq!(path_ids(&car, 3, 2), vec![3, 1, 2]);
+        assert_eq!(path_ids(&foot, 3, 2), vec![3, 2]);
+        assert_eq!(path_ids(&car, 5, 4), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_oneway_reversible() {
+        let data = osm_data(
+            &[1, 2, 3],
+            vec![
+                way(
+                    10,
+                    &[1, 2],
+                    &[("highway", "primary"), ("oneway", "reversible")],
+                ),
+                way(11, &[2, 3], &[("highway", "primary")]),
+            ],
+        );
+
+        let excluded = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        let allowed = Profile {
+            allow_reversible: true,
+            ..Profile::car()
+        };
+        let allowed = Graph::from_osm_data_with_profile(&data, &allowed).unwrap();
+
+        assert!(!excluded.contains_node_id(1));
+        assert_eq!(path_ids(&allowed, 1, 3), vec![1, 2, 3]);
+        assert_eq!(path_ids(&allowed, 3, 1), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_profile_edge_costs() {
+        let data = osm_data(&[1, 2], vec![way(10, &[1, 2], &[("highway", "cycleway")])]);
+
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::bicycle()).unwrap();
+        let edge = graph.adj_list[&1].2[0];
+
+        assert_eq!(
+            edge.distance,
+            Graph::calculate_distance((50.8365, -0.7786), (50.8365, -0.7772))
+        );
+        assert_eq!(edge.weight, edge.distance * 0.8);
+    }
+
+    #[test]
+    fn test_fastest_and_shortest_path() {
+        // 1 -> 4 directly on a residential street, or around through 5 on the trunk road
+        let data = osm_data(
+            &[1, 4, 5],
+            vec![
+                way(10, &[1, 4], &[("highway", "residential")]),
+                way(
+                    11,
+                    &[1, 5, 4],
+                    &[
+                        ("highway", "trunk"),
+                        ("maxspeed", "70 mph"),
+                        ("maxspeed:type", "GB:nsl_dual"),
+                    ],
+                ),
+            ],
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        for algorithm in [
+            Algorithm::Dijkstra,
+            Algorithm::AStar,
+            Algorithm::BellmanFord,
+            Algorithm::Bidirectional,
+        ] {
+            let shortest = SearchOptions {
+                algorithm,
+                ..Default::default()
+            };
+            let fastest = SearchOptions {
+                algorithm,
+                metric: Metric::Fastest,
+                ..Default::default()
+            };
+            let ids = |options: &SearchOptions| -> Vec<u64> {
+                graph.search(1, 4, options).unwrap().route.node_ids()
+            };
+
+            assert_eq!(ids(&shortest), vec![1, 4], "{}", algorithm);
+            assert_eq!(ids(&fastest), vec![1, 5, 4], "{}", algorithm);
+        }
+    }
+
+    #[test]
+    fn test_edge_duration() {
+        let data = osm_data(
+            &[1, 2],
+            vec![way(
+                10,
+                &[1, 2],
+                &[("highway", "residential"), ("maxspeed", "20 mph")],
+            )],
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        let edge = graph.adj_list[&1].2[0];
+
+        assert!((edge.duration - edge.distance / 32.186_88 * 3600.0).abs() < 1e-3);
+        assert_eq!(edge.cost(Metric::Fastest), edge.duration);
+        assert_eq!(edge.cost(Metric::Shortest), edge.weight);
+    }
+
+    #[test]
+    fn test_find_path_dispatch() {
+        let graph = sample_graph();
+
+        for algorithm in [
+            Algorithm::Dijkstra,
+            Algorithm::AStar,
+            Algorithm::BellmanFord,
+            Algorithm::Bidirectional,
+        ] {
+            assert_eq!(
+                graph.find_path(3, 4, algorithm),
+                Ok(graph.find_shortest_path(3, 4))
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_trace() {
+        let graph = sample_graph();
+
+        for algorithm in [
+            Algorithm::Dijkstra,
+            Algorithm::AStar,
+            Algorithm::BellmanFord,
+            Algorithm::Bidirectional,
+        ] {
+            let options = SearchOptions {
+                algorithm,
+                trace: true,
+                ..Default::default()
+            };
+            let result = graph.search(1, 5, &options).unwrap();
+            let trace = result.trace.expect("trace was requested");
+
+            assert_eq!(result.route, graph.find_shortest_path(1, 5));
+            assert_eq!(trace.settled.first().map(|node| node.id), Some(1));
+            // the bidirectional search reaches 4 <-> 5 from the end node
+            assert!(trace
+                .relaxed
+                .iter()
+                .any(|edge| (edge.from, edge.to) == (4, 5) || (edge.from, edge.to) == (5, 4)));
+            // every relaxed edge must start from a node reached before it
+            for edge in &trace.relaxed {
+                assert!(
+                    trace.settled.iter().any(|node| node.id == edge.from),
+                    "{} was never settled",
+                    edge.from
+                );
+            }
+        }
+
+        let result = graph.search(1, 5, &SearchOptions::default()).unwrap();
+        assert_eq!(result.trace, None);
+    }
+
+    #[test]
+    fn test_dijkstra_trace_is_ordered() {
+        let graph = sample_graph();
+        let options = SearchOptions {
+            trace: true,
+            ..Default::default()
+        };
+
+        let trace = graph.search(1, 3, &options).unwrap().trace.unwrap();
+        let costs: Vec<f32> = trace.settled.iter().map(|node| node.cost).collect();
 
-        assert_eq!(graph.find_shortest_path(1, 3), vec![(1, 51.5074, 0.1278), (4, 51.5074, 0.20005), (3, 51.5074, 0.27230)]);
-        assert_eq!(graph.find_shortest_path(3, 1), Vec::<(u64, f64, f64)>::new());
+        assert!(costs.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(trace.settled.last().map(|node| node.id), Some(3));
     }
 }
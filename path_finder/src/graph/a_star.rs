@@ -0,0 +1,134 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+
+use super::{Graph, Route};
+use crate::{metric::Metric, trace::Tracer};
+
+impl Graph {
+    /// Finds the shortest path using `A*` search
+    /// The heuristic is the `Haversine` distance from a node to the end node,
+    /// which never overestimates the remaining road distance so the path matches Dijkstra
+    pub fn find_shortest_path_astar(&self, start: u64, end: u64) -> Route {
+        self.to_route(
+            &self.astar(start, end, Metric::Shortest, Tracer::new(self, None)),
+            Metric::Shortest,
+        )
+    }
+
+    /// For the fastest metric the distance is turned into the time it takes at the top speed of the
+    /// graph
+    pub(super) fn astar(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        tracer: Tracer,
+    ) -> Vec<(u64, f64, f64)> {
+        if !self.contains_node_id(end) {
+            return Vec::new();
+        }
+
+        self.astar_with(
+            start,
+            end,
+            metric,
+            self.distance_heuristic(end, metric),
+            tracer,
+        )
+    }
+
+    /// Lower bound on the cost from a node to the end from the `Haversine` distance between them
+    pub(super) fn distance_heuristic(&self, end: u64, metric: Metric) -> impl Fn(u64) -> f32 + '_ {
+        let end_coordinates = self.coordinates(end);
+        move |node: u64| match (self.coordinates(node), end_coordinates) {
+            (Some(coordinates), Some(end)) => {
+                self.lower_bound(Graph::calculate_distance(coordinates, end), metric)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// `A*` guided by `heuristic`, which must never overestimate the remaining cost to the end
+    pub(super) fn astar_with<H>(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        heuristic: H,
+        mut tracer: Tracer,
+    ) -> Vec<(u64, f64, f64)>
+    where
+        H: Fn(u64) -> f32,
+    {
+        let mut distances: HashMap<u64, f32> = HashMap::new();
+        let mut predecessors: HashMap<u64, u64> = HashMap::new();
+        // ordered by the estimated total cost through the node
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>> = BinaryHeap::new();
+
+        distances.insert(start, 0.0);
+        heap.push(Reverse((OrderedFloat(heuristic(start)), start)));
+
+        while let Some(Reverse((estimate, node))) = heap.pop() {
+            let cost = OrderedFloat(distances[&node]);
+            if estimate > cost + heuristic(node) {
+                continue;
+            }
+
+            tracer.settled(node, cost.into_inner());
+            if node == end {
+                break;
+            }
+
+            if let Some((_, _, edges)) = self.adj_list.get(&node) {
+                for edge in edges {
+                    let neighbour = edge.to;
+                    let new_cost = cost + edge.cost(metric);
+                    if new_cost < OrderedFloat(*distances.get(&neighbour).unwrap_or(&f32::MAX)) {
+                        distances.insert(neighbour, new_cost.into_inner());
+                        predecessors.insert(neighbour, node);
+                        tracer.relaxed(node, neighbour, new_cost.into_inner());
+                        heap.push(Reverse((new_cost + heuristic(neighbour), neighbour)));
+                    }
+                }
+            }
+        }
+
+        self.build_path(start, end, &predecessors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::tests::{one_way_graph, sample_graph};
+
+    #[test]
+    fn test_astar_matches_dijkstra() {
+        for graph in [sample_graph(), one_way_graph()] {
+            for start in 1..=5 {
+                for end in 1..=5 {
+                    assert_eq!(
+                        graph.find_shortest_path_astar(start, end),
+                        graph.find_shortest_path(start, end),
+                        "paths differ for {} -> {}",
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_astar_unknown_node() {
+        let graph = sample_graph();
+
+        assert_eq!(
+            graph.find_shortest_path_astar(1, 42).path(),
+            Vec::<(u64, f64, f64)>::new()
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use super::Graph;
+use crate::r_tree::bbox_envelope;
+
+/// The nodes of an area and the edges between them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SubNetwork {
+    /// Sorted by id
+    pub nodes: Vec<(u64, f64, f64)>,
+    pub edges: Vec<NetworkEdge>,
+}
+
+/// A directed edge of a `SubNetwork`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkEdge {
+    pub from: u64,
+    pub to: u64,
+    /// Length in metres
+    pub distance: f32,
+    /// Travel time in seconds
+    pub duration: f32,
+    pub way_id: Option<u64>,
+}
+
+impl Graph {
+    /// Ids of the nodes inside the box or on its border, sorted
+    /// The box can't cross the antimeridian, one with its minimum above its maximum is empty
+    pub fn nodes_in_bbox(
+        &self,
+        min_lat: f64,
+        min_lon: f64,
+        max_lat: f64,
+        max_lon: f64,
+    ) -> Vec<u64> {
+        if min_lat > max_lat || min_lon > max_lon {
+            return Vec::new();
+        }
+
+        let mut ids: Vec<u64> = self
+            .rtree
+            .locate_in_envelope_intersecting(&bbox_envelope(min_lat, min_lon, max_lat, max_lon))
+            .filter(|node| {
+                (min_lat..=max_lat).contains(&node.lat) && (min_lon..=max_lon).contains(&node.lon)
+            })
+            .map(|node| node.id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The nodes inside the box and the edges between them
+    pub fn network_in_bbox(
+        &self,
+        min_lat: f64,
+        min_lon: f64,
+        max_lat: f64,
+        max_lon: f64,
+    ) -> SubNetwork {
+        self.network_of(&self.nodes_in_bbox(min_lat, min_lon, max_lat, max_lon))
+    }
+
+    /// The nodes within `radius` metres of the point and the edges between them
+    pub fn network_within_radius(&self, lat: f64, lon: f64, radius: f64) -> SubNetwork {
+        let mut ids: Vec<u64> = self
+            .nodes_within_radius(lat, lon, radius)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        self.network_of(&ids)
+    }
+
+    /// A graph of only the nodes inside the box and the edges between them, keeping their costs and
+    /// ways, the turn restrictions through those nodes, the turn costs and the profile
+    pub fn clip_to_bbox(&self, min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Graph {
+        let ids: HashSet<u64> = self
+            .nodes_in_bbox(min_lat, min_lon, max_lat, max_lon)
+            .into_iter()
+            .collect();
+        let mut graph = Graph::new();
+        graph.turn_costs = self.turn_costs;
+        graph.profile = self.profile.clone();
+
+        for &from in &ids {
+            let (lat, lon, edges) = &self.adj_list[&from];
+            for edge in edges.iter().filter(|edge| ids.contains(&edge.to)) {
+                let (to_lat, to_lon, _) = self.adj_list[&edge.to];
+                graph.add_edge((from, *lat, *lon), (edge.to, to_lat, to_lon), *edge);
+                if let Some((way_id, way)) =
+                    edge.way_id.and_then(|id| Some((id, self.ways.get(&id)?)))
+                {
+                    graph.ways.insert(way_id, way.clone());
+                }
+            }
+        }
+        for (via, restrictions) in self
+            .restrictions
+            .iter()
+            .filter(|(via, _)| ids.contains(via))
+        {
+            graph.restrictions.insert(*via, restrictions.clone());
+        }
+
+        graph
+    }
+
+    /// The nodes, which have to be sorted, and every edge between two of them
+    fn network_of(&self, ids: &[u64]) -> SubNetwork {
+        let inside: HashSet<u64> = ids.iter().copied().collect();
+        let nodes = self.with_coordinates(ids);
+        let edges = ids
+            .iter()
+            .flat_map(|&from| {
+                self.adj_list[&from]
+                    .2
+                    .iter()
+                    .filter(|edge| inside.contains(&edge.to))
+                    .map(move |edge| NetworkEdge {
+                        from,
+                        to: edge.to,
+                        distance: edge.distance * 1000.0,
+                        duration: edge.duration,
+                        way_id: edge.way_id,
+                    })
+            })
+            .collect();
+
+        SubNetwork { nodes, edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        graph::{
+            tests::{osm_data_at, random_graph, way},
+            Graph,
+        },
+        profile::Profile,
+    };
+
+    /// 1 - 2 - 3 along a parallel with 4 north of 2, and 5 far to the east
+    fn small_town() -> Graph {
+        let mut graph = Graph::new();
+        graph.add_edge_two_way((1, 50.0, 0.0), (2, 50.0, 0.01));
+        graph.add_edge_two_way((2, 50.0, 0.01), (3, 50.0, 0.02));
+        graph.add_edge_one_way((2, 50.0, 0.01), (4, 50.01, 0.01));
+        graph.add_edge_two_way((3, 50.0, 0.02), (5, 50.0, 0.5));
+        graph
+    }
+
+    #[test]
+    fn test_network_in_bbox() {
+        let graph = small_town();
+
+        let network = graph.network_in_bbox(49.99, -0.001, 50.02, 0.015);
+
+        assert_eq!(
+            network.nodes.iter().map(|node| node.0).collect::<Vec<_>>(),
+            vec![1, 2, 4]
+        );
+        let edges: HashSet<(u64, u64)> = network
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to))
+            .collect();
+        assert_eq!(edges, HashSet::from([(1, 2), (2, 1), (2, 4)]));
+        assert!(network
+            .edges
+            .iter()
+            .all(|edge| edge.distance > 0.0 && edge.duration > 0.0));
+        // 0.01 degrees of longitude, in metres like the distances of routes
+        let edge = network
+            .edges
+            .iter()
+            .find(|edge| (edge.from, edge.to) == (1, 2))
+            .unwrap();
+        assert!((edge.distance - 715.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_nodes_in_bbox_matches_brute_force() {
+        let graph = random_graph(4, 300, 600);
+
+        for (min_lat, min_lon, max_lat, max_lon) in [
+            (50.83, -0.79, 50.84, -0.77),
+            (50.82, -0.8, 50.85, -0.75),
+            (51.0, 0.0, 51.1, 0.1),
+        ] {
+            let mut expected: Vec<u64> = (0..300)
+                .filter_map(|id| graph.coordinates(id).map(|coordinates| (id, coordinates)))
+                .filter(|(_, (lat, lon))| {
+                    (min_lat..=max_lat).contains(lat) && (min_lon..=max_lon).contains(lon)
+                })
+                .map(|(id, _)| id)
+                .collect();
+            expected.sort_unstable();
+
+            assert_eq!(
+                graph.nodes_in_bbox(min_lat, min_lon, max_lat, max_lon),
+                expected
+            );
+        }
+        assert!(graph.nodes_in_bbox(50.85, -0.75, 50.82, -0.8).is_empty());
+    }
+
+    #[test]
+    fn test_network_within_radius() {
+        let graph = small_town();
+
+        // 2 is 0.01 degrees of longitude, about 715m, from 1 and 3, and 4 is 1112m north
+        let network = graph.network_within_radius(50.0, 0.01, 800.0);
+
+        assert_eq!(
+            network.nodes.iter().map(|node| node.0).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(network.edges.len(), 4);
+        assert!(graph
+            .network_within_radius(49.0, 0.0, 100.0)
+            .nodes
+            .is_empty());
+    }
+
+    #[test]
+    fn test_clip_to_bbox() {
+        let graph = small_town();
+
+        let clipped = graph.clip_to_bbox(49.99, -0.001, 50.02, 0.025);
+
+        assert!(!clipped.contains_node_id(5));
+        assert_eq!(clipped.find_shortest_path(1, 4).nodes.len(), 3);
+        assert!(clipped.find_shortest_path(4, 1).is_empty());
+        assert_eq!(clipped.nearest_neighbor(50.0, 0.5), Some(3));
+        assert_eq!(
+            clipped.network_in_bbox(-90.0, -180.0, 90.0, 180.0),
+            graph.network_in_bbox(49.99, -0.001, 50.02, 0.025)
+        );
+    }
+
+    #[test]
+    fn test_clip_to_bbox_keeps_profile() {
+        let data = osm_data_at(
+            &[(1, 50.0, 0.0), (2, 50.0, 0.01), (3, 50.0, 0.5)],
+            vec![way(100, &[1, 2, 3], &[("highway", "residential")])],
+            Vec::new(),
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::bicycle()).unwrap();
+
+        let clipped = graph.clip_to_bbox(49.99, -0.001, 50.02, 0.025);
+
+        assert_eq!(clipped.profile_name(), "bicycle");
+        assert_eq!(clipped.turn_costs(), graph.turn_costs());
+        assert!(!clipped.contains_node_id(3));
+    }
+}
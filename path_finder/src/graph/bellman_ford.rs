@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use super::{Graph, Route};
+use crate::{error::PathError, metric::Metric, trace::Tracer};
+
+impl Graph {
+    /// Finds the shortest path using `Bellman-Ford` with the distance weights of the graph
+    pub fn find_shortest_path_bellman_ford(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<Route, PathError> {
+        self.find_shortest_path_bellman_ford_with(start, end, |_, _, weight| weight)
+    }
+
+    /// Finds the shortest path using `Bellman-Ford` where every edge is weighted by `weight_fn`
+    /// The function gets the from node, to node and the distance of the edge and returns the weight
+    /// to use, which can be negative to give a bonus to an edge
+    /// Returns an error if a negative cycle is reachable from the start node
+    /// The cost of the route is still the one of the graph weights
+    pub fn find_shortest_path_bellman_ford_with<F>(
+        &self,
+        start: u64,
+        end: u64,
+        weight_fn: F,
+    ) -> Result<Route, PathError>
+    where
+        F: Fn(u64, u64, f32) -> f32,
+    {
+        let path = self.bellman_ford(
+            start,
+            end,
+            Metric::Shortest,
+            weight_fn,
+            Tracer::new(self, None),
+        )?;
+        Ok(self.to_route(&path, Metric::Shortest))
+    }
+
+    /// `weight_fn` gets the cost of each edge for the metric
+    /// Nodes are traced as settled the first time they are reached,
+    /// as Bellman-Ford only knows the final costs once every pass is done
+    pub(super) fn bellman_ford<F>(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        weight_fn: F,
+        mut tracer: Tracer,
+    ) -> Result<Vec<(u64, f64, f64)>, PathError>
+    where
+        F: Fn(u64, u64, f32) -> f32,
+    {
+        let mut distances: HashMap<u64, f32> = HashMap::new();
+        let mut predecessors: HashMap<u64, u64> = HashMap::new();
+
+        distances.insert(start, 0.0);
+        tracer.settled(start, 0.0);
+
+        // a shortest path has at most |V| - 1 edges, so that many passes are enough
+        for _ in 1..self.adj_list.len() {
+            if !self.relax_all_edges(
+                &mut distances,
+                &mut predecessors,
+                metric,
+                &weight_fn,
+                &mut tracer,
+            ) {
+                break;
+            }
+        }
+
+        // anything that still relaxes is on, or reachable from, a negative cycle
+        if let Some((node, neighbour)) = self.find_relaxable_edge(&distances, metric, &weight_fn) {
+            predecessors.insert(neighbour, node);
+            let mut on_cycle = neighbour;
+            // walking back |V| predecessors is guaranteed to end up inside the cycle
+            for _ in 0..self.adj_list.len() {
+                match predecessors.get(&on_cycle) {
+                    Some(&prev) => on_cycle = prev,
+                    None => break,
+                }
+            }
+            return Err(PathError::NegativeCycle(on_cycle));
+        }
+
+        Ok(self.build_path(start, end, &predecessors))
+    }
+
+    /// Relaxes every edge leaving an already reached node once
+    /// Returns whether any distance was improved
+    fn relax_all_edges<F>(
+        &self,
+        distances: &mut HashMap<u64, f32>,
+        predecessors: &mut HashMap<u64, u64>,
+        metric: Metric,
+        weight_fn: &F,
+        tracer: &mut Tracer,
+    ) -> bool
+    where
+        F: Fn(u64, u64, f32) -> f32,
+    {
+        let mut changed = false;
+
+        for (&node, (_, _, edges)) in &self.adj_list {
+            let Some(&cost) = distances.get(&node) else {
+                continue;
+            };
+
+            for edge in edges {
+                let neighbour = edge.to;
+                let new_cost = cost + weight_fn(node, neighbour, edge.cost(metric));
+                if new_cost < *distances.get(&neighbour).unwrap_or(&f32::MAX) {
+                    if distances.insert(neighbour, new_cost).is_none() {
+                        tracer.settled(neighbour, new_cost);
+                    }
+                    predecessors.insert(neighbour, node);
+                    tracer.relaxed(node, neighbour, new_cost);
+                    changed = true;
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Returns the `(from, to)` of an edge that can still be relaxed, if any
+    fn find_relaxable_edge<F>(
+        &self,
+        distances: &HashMap<u64, f32>,
+        metric: Metric,
+        weight_fn: &F,
+    ) -> Option<(u64, u64)>
+    where
+        F: Fn(u64, u64, f32) -> f32,
+    {
+        self.adj_list.iter().find_map(|(&node, (_, _, edges))| {
+            let cost = *distances.get(&node)?;
+            edges.iter().find_map(|edge| {
+                let new_cost = cost + weight_fn(node, edge.to, edge.cost(metric));
+                (new_cost < *distances.get(&edge.to).unwrap_or(&f32::MAX))
+                    .then_some((node, edge.to))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::PathError,
+        graph::tests::{one_way_graph, path_cost, sample_graph},
+    };
+
+    #[test]
+    fn test_bellman_ford_matches_dijkstra() {
+        for graph in [sample_graph(), one_way_graph()] {
+            for start in 1..=5 {
+                for end in 1..=5 {
+                    assert_eq!(
+                        graph.find_shortest_path_bellman_ford(start, end),
+                        Ok(graph.find_shortest_path(start, end)),
+                        "paths differ for {} -> {}",
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bellman_ford_penalty_weights() {
+        let graph = sample_graph();
+
+        // making 1 <-> 4 expensive routes through 2 instead
+        let path = graph
+            .find_shortest_path_bellman_ford_with(1, 5, |from, to, weight| match (from, to) {
+                (1, 4) | (4, 1) => weight * 10.0,
+                _ => weight,
+            })
+            .unwrap();
+        assert_eq!(path.node_ids(), vec![1, 2, 5]);
+    }
+
+    #[test]
+    fn test_bellman_ford_negative_weights() {
+        let graph = one_way_graph();
+
+        // a bonus on the one way 4 -> 3 makes the detour through 1 cheaper than 2 -> 3
+        let path = graph
+            .find_shortest_path_bellman_ford_with(2, 3, |from, to, weight| match (from, to) {
+                (4, 3) => -20.0,
+                _ => weight,
+            })
+            .unwrap();
+        assert_eq!(path.node_ids(), vec![2, 1, 4, 3]);
+        // the route still costs what the graph weights say
+        assert!((path.cost - path_cost(&graph, &path)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_bellman_ford_negative_cycle() {
+        let graph = sample_graph();
+
+        let result =
+            graph.find_shortest_path_bellman_ford_with(3, 4, |from, to, weight| match (from, to) {
+                (1, 2) | (2, 1) => -1.0,
+                _ => weight,
+            });
+
+        assert!(matches!(result, Err(PathError::NegativeCycle(node)) if node == 1 || node == 2));
+    }
+}
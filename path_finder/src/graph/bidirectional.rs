@@ -0,0 +1,214 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+
+use super::{Graph, Route};
+use crate::{metric::Metric, trace::Tracer};
+
+/// Frontier of one direction of the bidirectional search
+struct Frontier {
+    distances: HashMap<u64, f32>,
+    predecessors: HashMap<u64, u64>,
+    heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>>,
+}
+
+impl Frontier {
+    fn new(source: u64) -> Self {
+        let mut frontier = Frontier {
+            distances: HashMap::new(),
+            predecessors: HashMap::new(),
+            heap: BinaryHeap::new(),
+        };
+        frontier.distances.insert(source, 0.0);
+        frontier.heap.push(Reverse((OrderedFloat(0.0), source)));
+        frontier
+    }
+
+    /// The smallest cost still waiting on the heap
+    fn min_cost(&self) -> Option<f32> {
+        self.heap.peek().map(|Reverse((cost, _))| cost.into_inner())
+    }
+}
+
+impl Graph {
+    /// Finds the shortest path by searching forward from the start and backward from the end at the
+    /// same time
+    /// The backward search follows the reverse adjacency list, so one way streets are respected
+    /// Stops once the two frontiers can no longer improve the best meeting point
+    pub fn find_shortest_path_bidirectional(&self, start: u64, end: u64) -> Route {
+        let path =
+            self.bidirectional_dijkstra(start, end, Metric::Shortest, Tracer::new(self, None));
+        self.to_route(&path, Metric::Shortest)
+    }
+
+    /// Both directions are traced into the same trace, each with the cost from its own source
+    pub(super) fn bidirectional_dijkstra(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        mut tracer: Tracer,
+    ) -> Vec<(u64, f64, f64)> {
+        if !self.contains_node_id(start) || !self.contains_node_id(end) {
+            return Vec::new();
+        }
+
+        let mut forward = Frontier::new(start);
+        let mut backward = Frontier::new(end);
+        let mut best = if start == end { 0.0 } else { f32::MAX };
+        let mut meeting = (start == end).then_some(start);
+
+        while let (Some(forward_min), Some(backward_min)) =
+            (forward.min_cost(), backward.min_cost())
+        {
+            if forward_min + backward_min >= best {
+                break;
+            }
+
+            let is_forward = forward_min <= backward_min;
+            let (frontier, other) = if is_forward {
+                (&mut forward, &backward)
+            } else {
+                (&mut backward, &forward)
+            };
+
+            let Some(Reverse((cost, node))) = frontier.heap.pop() else {
+                break;
+            };
+            if cost > OrderedFloat(frontier.distances[&node]) {
+                continue;
+            }
+            tracer.settled(node, cost.into_inner());
+
+            let neighbours: Vec<(u64, f32)> = if is_forward {
+                self.adj_list
+                    .get(&node)
+                    .map_or_else(Vec::new, |(_, _, edges)| {
+                        edges
+                            .iter()
+                            .map(|edge| (edge.to, edge.cost(metric)))
+                            .collect()
+                    })
+            } else {
+                self.incoming_edges(node, metric)
+            };
+
+            for (neighbour, weight) in neighbours {
+                let new_cost = cost + weight;
+                if new_cost < OrderedFloat(*frontier.distances.get(&neighbour).unwrap_or(&f32::MAX))
+                {
+                    frontier.distances.insert(neighbour, new_cost.into_inner());
+                    frontier.predecessors.insert(neighbour, node);
+                    tracer.relaxed(node, neighbour, new_cost.into_inner());
+                    frontier.heap.push(Reverse((new_cost, neighbour)));
+                }
+
+                if let Some(&other_cost) = other.distances.get(&neighbour) {
+                    let through = frontier.distances[&neighbour] + other_cost;
+                    if through < best {
+                        best = through;
+                        meeting = Some(neighbour);
+                    }
+                }
+            }
+        }
+
+        let Some(meeting) = meeting else {
+            return Vec::new();
+        };
+
+        let mut path = self.build_path(start, meeting, &forward.predecessors);
+        // the backward predecessors point towards the end node
+        let mut current = meeting;
+        while let Some(&next) = backward.predecessors.get(&current) {
+            if let Some((lat, lon)) = self.coordinates(next) {
+                path.push((next, lat, lon));
+            }
+            current = next;
+        }
+        path
+    }
+
+    /// Returns the `(from, cost)` of every edge arriving at the node
+    pub(super) fn incoming_edges(&self, node: u64, metric: Metric) -> Vec<(u64, f32)> {
+        self.rev_adj_list
+            .get(&node)
+            .map_or_else(Vec::new, |incoming| {
+                incoming
+                    .iter()
+                    .map(|&(from, index)| (from, self.adj_list[&from].2[index].cost(metric)))
+                    .collect()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::tests::{one_way_graph, path_cost, random_graph, sample_graph, Lcg};
+
+    #[test]
+    fn test_bidirectional_matches_dijkstra() {
+        for graph in [sample_graph(), one_way_graph()] {
+            for start in 1..=5 {
+                for end in 1..=5 {
+                    assert_eq!(
+                        graph.find_shortest_path_bidirectional(start, end),
+                        graph.find_shortest_path(start, end),
+                        "paths differ for {} -> {}",
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bidirectional_random_pairs() {
+        let mut rng = Lcg(7);
+
+        for seed in 0..5 {
+            let graph = random_graph(seed, 80, 160);
+
+            for _ in 0..50 {
+                let start = rng.next_below(80);
+                let end = rng.next_below(80);
+                let expected = graph.find_shortest_path(start, end);
+                let path = graph.find_shortest_path_bidirectional(start, end);
+
+                assert_eq!(
+                    path.is_empty(),
+                    expected.is_empty(),
+                    "reachability differs for {} -> {}",
+                    start,
+                    end
+                );
+                if !path.is_empty() {
+                    assert_eq!(path.nodes.first().map(|node| node.id), Some(start));
+                    assert_eq!(path.nodes.last().map(|node| node.id), Some(end));
+                    assert!(
+                        (path_cost(&graph, &path) - path_cost(&graph, &expected)).abs() < 1e-3,
+                        "cost differs for {} -> {}",
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bidirectional_respects_one_way() {
+        let graph = one_way_graph();
+
+        // 3 can only reach 5, the backward search must not walk 4 -> 3 the wrong way
+        assert!(graph.find_shortest_path_bidirectional(3, 4).is_empty());
+        assert_eq!(
+            graph.find_shortest_path_bidirectional(1, 5).node_ids(),
+            vec![1, 4, 3, 5]
+        );
+    }
+}
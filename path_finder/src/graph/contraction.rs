@@ -0,0 +1,580 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use super::{Graph, Route};
+use crate::{error::PathError, metric::Metric, trace::Tracer};
+
+/// Witness searches give up after settling this many nodes and add the shortcut anyway
+/// A few unnecessary shortcuts keep the queries correct, only a little slower
+const WITNESS_SETTLE_LIMIT: usize = 100;
+
+/// Cheapest edge between each pair of remaining nodes while contracting, with the node a shortcut
+/// skips over
+type WorkingEdges = HashMap<u64, HashMap<u64, (f32, Option<u64>)>>;
+
+/// Edge of the hierarchy, `via` is the node a shortcut skips over
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct HierarchyEdge {
+    to: u64,
+    cost: f32,
+    via: Option<u64>,
+}
+
+/// Contraction hierarchy of a graph for one metric
+/// Nodes are contracted from the least to the most important, adding shortcuts so that
+/// every shortest path goes up the hierarchy and then back down, which a query explores from both
+/// ends
+/// Turn restrictions and turn costs are not taken into account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractionHierarchy {
+    metric: Metric,
+    /// Contraction order of every node, higher ranks were contracted later
+    ranks: HashMap<u64, usize>,
+    /// Edges to higher ranked nodes, followed forward from the start
+    up: HashMap<u64, Vec<HierarchyEdge>>,
+    /// Edges arriving from higher ranked nodes, followed backward from the end
+    down: HashMap<u64, Vec<HierarchyEdge>>,
+    shortcuts: usize,
+}
+
+/// One direction of a query
+struct UpwardSearch {
+    distances: HashMap<u64, f32>,
+    predecessors: HashMap<u64, u64>,
+    heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>>,
+}
+
+impl UpwardSearch {
+    fn new(source: u64) -> Self {
+        let mut search = UpwardSearch {
+            distances: HashMap::new(),
+            predecessors: HashMap::new(),
+            heap: BinaryHeap::new(),
+        };
+        search.distances.insert(source, 0.0);
+        search.heap.push(Reverse((OrderedFloat(0.0), source)));
+        search
+    }
+
+    /// The smallest cost still waiting on the heap if it can still beat `best`
+    fn min_cost_below(&self, best: f32) -> Option<f32> {
+        self.heap
+            .peek()
+            .map(|Reverse((cost, _))| cost.into_inner())
+            .filter(|&cost| cost < best)
+    }
+}
+
+impl ContractionHierarchy {
+    /// Contracts every node of the graph using the edge costs of the metric
+    pub fn new(graph: &Graph, metric: Metric) -> Self {
+        let mut outgoing: WorkingEdges = graph
+            .adj_list
+            .keys()
+            .map(|&node| (node, HashMap::new()))
+            .collect();
+        let mut incoming = outgoing.clone();
+        for (&from, (_, _, edges)) in &graph.adj_list {
+            for edge in edges.iter().filter(|edge| edge.to != from) {
+                insert_cheapest(&mut outgoing, from, edge.to, edge.cost(metric), None);
+                insert_cheapest(&mut incoming, edge.to, from, edge.cost(metric), None);
+            }
+        }
+
+        let mut contractor = Contractor {
+            outgoing,
+            incoming,
+            contracted_neighbours: HashMap::new(),
+        };
+        let mut hierarchy = ContractionHierarchy {
+            metric,
+            ranks: HashMap::new(),
+            up: HashMap::new(),
+            down: HashMap::new(),
+            shortcuts: 0,
+        };
+
+        let mut nodes: Vec<u64> = graph.adj_list.keys().copied().collect();
+        nodes.sort_unstable();
+        let mut queue: BinaryHeap<Reverse<(i64, u64)>> = nodes
+            .into_iter()
+            .map(|node| Reverse((contractor.priority(node), node)))
+            .collect();
+
+        // contracting a neighbour changes the priority of a node, so the popped one is recomputed
+        // and put back when it is no longer the lowest
+        while let Some(Reverse((_, node))) = queue.pop() {
+            let priority = contractor.priority(node);
+            if queue
+                .peek()
+                .is_some_and(|Reverse((next, _))| priority > *next)
+            {
+                queue.push(Reverse((priority, node)));
+                continue;
+            }
+
+            contractor.contract(node, &mut hierarchy);
+            hierarchy.ranks.insert(node, hierarchy.ranks.len());
+        }
+
+        hierarchy
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Position of the node in the contraction order
+    pub fn rank(&self, node: u64) -> Option<usize> {
+        self.ranks.get(&node).copied()
+    }
+
+    /// Number of shortcuts added while contracting
+    pub fn shortcut_count(&self) -> usize {
+        self.shortcuts
+    }
+
+    /// Node ids of the shortest path with every shortcut unpacked, empty if the end can't be
+    /// reached
+    fn query(&self, start: u64, end: u64, tracer: &mut Tracer) -> Vec<u64> {
+        if !self.ranks.contains_key(&start) || !self.ranks.contains_key(&end) {
+            return Vec::new();
+        }
+
+        let mut forward = UpwardSearch::new(start);
+        let mut backward = UpwardSearch::new(end);
+        let mut best = f32::MAX;
+        let mut meeting = None;
+
+        loop {
+            // the upward searches can't stop at the first meeting point, only once neither can
+            // improve on it
+            let (search, other, edges) =
+                match (forward.min_cost_below(best), backward.min_cost_below(best)) {
+                    (Some(f), Some(b)) if b < f => (&mut backward, &forward, &self.down),
+                    (Some(_), _) => (&mut forward, &backward, &self.up),
+                    (None, Some(_)) => (&mut backward, &forward, &self.down),
+                    (None, None) => break,
+                };
+
+            let Some(Reverse((cost, node))) = search.heap.pop() else {
+                break;
+            };
+            let cost = cost.into_inner();
+            if cost > search.distances[&node] {
+                continue;
+            }
+
+            tracer.settled(node, cost);
+            if let Some(&other_cost) = other.distances.get(&node) {
+                if cost + other_cost < best {
+                    best = cost + other_cost;
+                    meeting = Some(node);
+                }
+            }
+
+            for edge in edges.get(&node).into_iter().flatten() {
+                let new_cost = cost + edge.cost;
+                if new_cost < *search.distances.get(&edge.to).unwrap_or(&f32::MAX) {
+                    search.distances.insert(edge.to, new_cost);
+                    search.predecessors.insert(edge.to, node);
+                    tracer.relaxed(node, edge.to, new_cost);
+                    search.heap.push(Reverse((OrderedFloat(new_cost), edge.to)));
+                }
+            }
+        }
+
+        let Some(meeting) = meeting else {
+            return Vec::new();
+        };
+
+        // start up to the meeting node, then down to the end
+        let mut hops = vec![meeting];
+        while let Some(&prev) = forward.predecessors.get(hops.last().unwrap()) {
+            hops.push(prev);
+        }
+        hops.reverse();
+        while let Some(&next) = backward.predecessors.get(hops.last().unwrap()) {
+            hops.push(next);
+        }
+
+        let mut path = vec![start];
+        for pair in hops.windows(2) {
+            self.unpack(pair[0], pair[1], &mut path);
+        }
+        path
+    }
+
+    /// Pushes the nodes after `from` on the way to `to`, replacing shortcuts with the edges they
+    /// skip
+    fn unpack(&self, from: u64, to: u64, path: &mut Vec<u64>) {
+        match self.edge(from, to).and_then(|edge| edge.via) {
+            Some(via) => {
+                self.unpack(from, via, path);
+                self.unpack(via, to, path);
+            }
+            None => path.push(to),
+        }
+    }
+
+    /// The edge from -> to, which is stored with the lower ranked of the two nodes
+    fn edge(&self, from: u64, to: u64) -> Option<&HierarchyEdge> {
+        let (edges, other) = if self.ranks.get(&from) < self.ranks.get(&to) {
+            (self.up.get(&from), to)
+        } else {
+            (self.down.get(&to), from)
+        };
+        edges?.iter().find(|edge| edge.to == other)
+    }
+}
+
+/// Graph of the nodes that are not contracted yet
+struct Contractor {
+    outgoing: WorkingEdges,
+    incoming: WorkingEdges,
+    /// Spreads the contraction over the graph instead of working through one area at a time
+    contracted_neighbours: HashMap<u64, usize>,
+}
+
+impl Contractor {
+    /// Edge difference of contracting the node, nodes with the lowest priority are contracted first
+    fn priority(&self, node: u64) -> i64 {
+        let removed = self.outgoing[&node].len() + self.incoming[&node].len();
+        let added = self.shortcuts(node).len();
+        let neighbours = self.contracted_neighbours.get(&node).copied().unwrap_or(0);
+
+        added as i64 - removed as i64 + neighbours as i64
+    }
+
+    /// Moves the edges of the node into the hierarchy and adds the shortcuts that replace it
+    fn contract(&mut self, node: u64, hierarchy: &mut ContractionHierarchy) {
+        let shortcuts = self.shortcuts(node);
+        let outgoing = self.outgoing.remove(&node).unwrap_or_default();
+        let incoming = self.incoming.remove(&node).unwrap_or_default();
+
+        for (&to, &(cost, via)) in &outgoing {
+            self.incoming.get_mut(&to).map(|edges| edges.remove(&node));
+            *self.contracted_neighbours.entry(to).or_default() += 1;
+            hierarchy
+                .up
+                .entry(node)
+                .or_default()
+                .push(HierarchyEdge { to, cost, via });
+        }
+        for (&from, &(cost, via)) in &incoming {
+            self.outgoing
+                .get_mut(&from)
+                .map(|edges| edges.remove(&node));
+            *self.contracted_neighbours.entry(from).or_default() += 1;
+            hierarchy.down.entry(node).or_default().push(HierarchyEdge {
+                to: from,
+                cost,
+                via,
+            });
+        }
+
+        for (from, to, cost) in shortcuts {
+            if insert_cheapest(&mut self.outgoing, from, to, cost, Some(node)) {
+                insert_cheapest(&mut self.incoming, to, from, cost, Some(node));
+                hierarchy.shortcuts += 1;
+            }
+        }
+    }
+
+    /// `(from, to, cost)` of the shortcuts needed to keep the shortest paths through the node
+    /// when there is no other path, a witness, that is at most as cheap
+    fn shortcuts(&self, node: u64) -> Vec<(u64, u64, f32)> {
+        let mut shortcuts = Vec::new();
+        let (Some(incoming), Some(outgoing)) = (self.incoming.get(&node), self.outgoing.get(&node))
+        else {
+            return shortcuts;
+        };
+
+        for (&from, &(in_cost, _)) in incoming {
+            let limit = outgoing
+                .iter()
+                .filter(|(&to, _)| to != from)
+                .map(|(_, &(out_cost, _))| in_cost + out_cost)
+                .fold(0.0, f32::max);
+            let witnesses = self.witness_search(from, node, limit);
+
+            for (&to, &(out_cost, _)) in outgoing {
+                let cost = in_cost + out_cost;
+                if to != from && witnesses.get(&to).is_none_or(|&witness| witness > cost) {
+                    shortcuts.push((from, to, cost));
+                }
+            }
+        }
+
+        shortcuts
+    }
+
+    /// Costs from `source` found without going through `skip`, up to `limit`
+    fn witness_search(&self, source: u64, skip: u64, limit: f32) -> HashMap<u64, f32> {
+        let mut distances: HashMap<u64, f32> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>> = BinaryHeap::new();
+        let mut settled = 0;
+
+        distances.insert(source, 0.0);
+        heap.push(Reverse((OrderedFloat(0.0), source)));
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            let cost = cost.into_inner();
+            if cost > distances[&node] {
+                continue;
+            }
+            if cost > limit || settled >= WITNESS_SETTLE_LIMIT {
+                break;
+            }
+            settled += 1;
+
+            for (&neighbour, &(edge_cost, _)) in self.outgoing.get(&node).into_iter().flatten() {
+                let new_cost = cost + edge_cost;
+                if neighbour != skip && new_cost < *distances.get(&neighbour).unwrap_or(&f32::MAX) {
+                    distances.insert(neighbour, new_cost);
+                    heap.push(Reverse((OrderedFloat(new_cost), neighbour)));
+                }
+            }
+        }
+
+        distances
+    }
+}
+
+/// Keeps the cheaper of the new and the existing edge, returns whether the new one was kept
+fn insert_cheapest(
+    edges: &mut WorkingEdges,
+    from: u64,
+    to: u64,
+    cost: f32,
+    via: Option<u64>,
+) -> bool {
+    let edges = edges.entry(from).or_default();
+    if edges
+        .get(&to)
+        .is_some_and(|&(existing, _)| existing <= cost)
+    {
+        return false;
+    }
+    edges.insert(to, (cost, via));
+    true
+}
+
+impl Graph {
+    /// Builds the contraction hierarchy for the metric, replacing any earlier one
+    /// It is serialised with the graph, so it can be built once offline and loaded with it
+    pub fn build_contraction_hierarchy(&mut self, metric: Metric) {
+        self.contraction_hierarchy = Some(ContractionHierarchy::new(self, metric));
+    }
+
+    pub fn contraction_hierarchy(&self) -> Option<&ContractionHierarchy> {
+        self.contraction_hierarchy.as_ref()
+    }
+
+    /// Finds the shortest path with the contraction hierarchy, which has to be built for the
+    /// shortest metric
+    pub fn find_shortest_path_contraction_hierarchy(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<Route, PathError> {
+        let path = self.contraction_hierarchy_search(
+            start,
+            end,
+            Metric::Shortest,
+            Tracer::new(self, None),
+        )?;
+        Ok(self.to_route(&path, Metric::Shortest))
+    }
+
+    /// Both upward searches are traced into the same trace, each with the cost from its own source
+    pub(super) fn contraction_hierarchy_search(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        mut tracer: Tracer,
+    ) -> Result<Vec<(u64, f64, f64)>, PathError> {
+        let hierarchy = self
+            .contraction_hierarchy
+            .as_ref()
+            .filter(|hierarchy| hierarchy.metric == metric)
+            .ok_or(PathError::MissingContractionHierarchy(metric))?;
+
+        Ok(hierarchy
+            .query(start, end, &mut tracer)
+            .into_iter()
+            .filter_map(|node| self.coordinates(node).map(|(lat, lon)| (node, lat, lon)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm::Algorithm,
+        error::PathError,
+        graph::{
+            tests::{one_way_graph, osm_data_at, path_cost, random_graph, sample_graph, way},
+            Graph,
+        },
+        metric::Metric,
+        profile::Profile,
+        search::SearchOptions,
+    };
+
+    #[test]
+    fn test_contraction_hierarchy_matches_dijkstra() {
+        for mut graph in [sample_graph(), one_way_graph()] {
+            graph.build_contraction_hierarchy(Metric::Shortest);
+            for start in 1..=5 {
+                for end in 1..=5 {
+                    assert_eq!(
+                        graph.find_shortest_path_contraction_hierarchy(start, end),
+                        Ok(graph.find_shortest_path(start, end)),
+                        "paths differ for {} -> {}",
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_contraction_hierarchy_random_graphs() {
+        for seed in 0..5 {
+            let mut graph = random_graph(seed, 60, 150);
+            graph.build_contraction_hierarchy(Metric::Shortest);
+
+            for start in 0..60 {
+                for end in (0..60).step_by(7) {
+                    let expected = graph.find_shortest_path(start, end);
+                    let path = graph
+                        .find_shortest_path_contraction_hierarchy(start, end)
+                        .unwrap();
+
+                    assert_eq!(
+                        path.nodes.first(),
+                        expected.nodes.first(),
+                        "seed {} {} -> {}",
+                        seed,
+                        start,
+                        end
+                    );
+                    assert_eq!(
+                        path.nodes.last(),
+                        expected.nodes.last(),
+                        "seed {} {} -> {}",
+                        seed,
+                        start,
+                        end
+                    );
+                    assert!(
+                        (path_cost(&graph, &path) - path_cost(&graph, &expected)).abs() < 1e-3,
+                        "seed {} {} -> {}",
+                        seed,
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_contraction_hierarchy_adds_shortcuts() {
+        let mut graph = random_graph(1, 60, 150);
+        graph.build_contraction_hierarchy(Metric::Shortest);
+        let hierarchy = graph.contraction_hierarchy().unwrap();
+
+        assert!(hierarchy.shortcut_count() > 0);
+        let mut ranks: Vec<usize> = (0..60).filter_map(|node| hierarchy.rank(node)).collect();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..ranks.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_contraction_hierarchy_serde() {
+        let mut graph = random_graph(2, 40, 100);
+        graph.build_contraction_hierarchy(Metric::Shortest);
+
+        let loaded: Graph = serde_json::from_str(&graph.to_json()).unwrap();
+
+        assert_eq!(
+            loaded.contraction_hierarchy(),
+            graph.contraction_hierarchy()
+        );
+        // coordinates may lose their last digit in JSON, so only the nodes are compared
+        let ids = |graph: &Graph, end: u64| {
+            graph
+                .find_shortest_path_contraction_hierarchy(0, end)
+                .unwrap()
+                .node_ids()
+        };
+        for end in 0..40 {
+            assert_eq!(ids(&loaded, end), ids(&graph, end));
+        }
+    }
+
+    #[test]
+    fn test_contracted_graph_keeps_profile() {
+        let data = osm_data_at(
+            &[(1, 50.0, 0.0), (2, 50.0, 0.001), (3, 50.0, 0.002)],
+            vec![way(100, &[1, 2, 3], &[("highway", "residential")])],
+            vec![],
+        );
+        let mut graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        graph.build_contraction_hierarchy(Metric::Fastest);
+
+        let loaded: Graph = serde_json::from_str(&graph.to_json()).unwrap();
+
+        assert_eq!(loaded.profile_name(), "car");
+        assert_eq!(loaded.turn_costs(), &Profile::car().turn_costs);
+        assert_eq!(Graph::new().profile_name(), "");
+    }
+
+    #[test]
+    fn test_search_with_contraction_hierarchy() {
+        let mut graph = sample_graph();
+        let options = SearchOptions {
+            algorithm: Algorithm::ContractionHierarchy,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            graph.search(1, 5, &options),
+            Err(PathError::MissingContractionHierarchy(Metric::Shortest))
+        );
+
+        graph.build_contraction_hierarchy(Metric::Shortest);
+        assert_eq!(
+            graph.search(1, 5, &options).unwrap().route,
+            graph.find_shortest_path(1, 5)
+        );
+
+        let fastest = SearchOptions {
+            metric: Metric::Fastest,
+            ..options
+        };
+        assert_eq!(
+            graph.search(1, 5, &fastest),
+            Err(PathError::MissingContractionHierarchy(Metric::Fastest))
+        );
+    }
+
+    #[test]
+    fn test_adding_edges_drops_hierarchy() {
+        let mut graph = sample_graph();
+        graph.build_contraction_hierarchy(Metric::Shortest);
+
+        graph.add_edge_two_way((1, 51.5074, 0.1278), (5, 51.5, 0.2));
+
+        assert!(graph.contraction_hierarchy().is_none());
+    }
+}
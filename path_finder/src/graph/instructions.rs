@@ -0,0 +1,603 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use super::{Graph, Route, WayInfo};
+use crate::{
+    error::PathError,
+    metric::Metric,
+    search::SearchOptions,
+    turn_cost::{bearing, turn_angle},
+};
+
+/// What to do at the start of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Maneuver {
+    Depart,
+    Continue,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    SlightRight,
+    Right,
+    SharpRight,
+    UTurn,
+    /// Leave the roundabout at the `exit`th road leaving it, counting from the entry
+    Roundabout {
+        exit: usize,
+    },
+    Arrive,
+}
+
+impl Maneuver {
+    /// Turn for the angle from `turn_angle`, negative to the left
+    fn from_angle(angle: f64) -> Self {
+        let left = angle < 0.0;
+        match angle.abs() {
+            a if a <= 20.0 => Maneuver::Continue,
+            a if a <= 60.0 => {
+                if left {
+                    Maneuver::SlightLeft
+                } else {
+                    Maneuver::SlightRight
+                }
+            }
+            a if a <= 135.0 => {
+                if left {
+                    Maneuver::Left
+                } else {
+                    Maneuver::Right
+                }
+            }
+            a if a <= 170.0 => {
+                if left {
+                    Maneuver::SharpLeft
+                } else {
+                    Maneuver::SharpRight
+                }
+            }
+            _ => Maneuver::UTurn,
+        }
+    }
+}
+
+/// One step of turn by turn directions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Instruction {
+    pub maneuver: Maneuver,
+    /// The node the maneuver is made at
+    pub node: u64,
+    /// Name and road number of the road followed after the maneuver, when it has any
+    pub road: Option<String>,
+    /// Length in metres followed before the next instruction
+    pub distance: f32,
+    /// Travel time in seconds before the next instruction
+    pub duration: f32,
+    pub text: String,
+}
+
+/// A route with the directions to follow it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Directions {
+    pub route: Route,
+    pub instructions: Vec<Instruction>,
+}
+
+/// One edge of a path with the way it belongs to
+struct Step<'a> {
+    from: u64,
+    to: u64,
+    way_id: Option<u64>,
+    way: Option<&'a WayInfo>,
+    distance: f32,
+    duration: f32,
+}
+
+impl Step<'_> {
+    fn roundabout(&self) -> bool {
+        self.way.is_some_and(|way| way.roundabout)
+    }
+
+    /// Consecutive steps with the same key are on the same road, even when OSM splits it into
+    /// several ways
+    fn road_key(&self) -> (Option<&str>, Option<&str>, Option<u64>) {
+        let name = self.way.and_then(|way| way.name.as_deref());
+        let reference = self.way.and_then(|way| way.reference.as_deref());
+        match (name, reference) {
+            (None, None) => (None, None, self.way_id),
+            _ => (name, reference, None),
+        }
+    }
+}
+
+impl Graph {
+    /// Searches as described by the options and describes the route found
+    /// An unreachable end gives an empty route and no instructions
+    pub fn directions(
+        &self,
+        start: u64,
+        end: u64,
+        options: &SearchOptions,
+    ) -> Result<Directions, PathError> {
+        let route = self.search(start, end, options)?.route;
+        let instructions = self.instructions(&route.path(), options.metric);
+        Ok(Directions {
+            route,
+            instructions,
+        })
+    }
+
+    /// Turn by turn instructions for the path, one per road followed, a roundabout and the road
+    /// after it being one, then an arrival
+    /// The edges are the ones a search minimising the metric takes
+    pub fn instructions(&self, path: &[(u64, f64, f64)], metric: Metric) -> Vec<Instruction> {
+        let steps: Vec<Step> = path
+            .windows(2)
+            .filter_map(|pair| {
+                let edge = self.cheapest_edge(pair[0].0, pair[1].0, metric)?;
+                Some(Step {
+                    from: pair[0].0,
+                    to: pair[1].0,
+                    way_id: edge.way_id,
+                    way: edge.way_id.and_then(|id| self.ways.get(&id)),
+                    distance: edge.distance * 1000.0,
+                    duration: edge.duration,
+                })
+            })
+            .collect();
+        if steps.is_empty() {
+            return Vec::new();
+        }
+
+        // ranges of steps on one road
+        let mut groups: Vec<Range<usize>> = vec![Range { start: 0, end: 1 }];
+        for (index, pair) in steps.windows(2).enumerate() {
+            if pair[0].road_key() == pair[1].road_key()
+                && pair[0].roundabout() == pair[1].roundabout()
+            {
+                groups.last_mut().unwrap().end = index + 2;
+            } else {
+                groups.push(index + 1..index + 2);
+            }
+        }
+
+        let totals = |range: &Range<usize>| {
+            steps[range.clone()]
+                .iter()
+                .fold((0.0, 0.0), |(distance, duration), step| {
+                    (distance + step.distance, duration + step.duration)
+                })
+        };
+        let road = |range: &Range<usize>| steps[range.start].way.and_then(WayInfo::road_name);
+
+        let mut instructions = Vec::new();
+        let first = &groups[0];
+        let (distance, duration) = totals(first);
+        let heading = bearing(
+            self.coordinates(steps[0].from).unwrap(),
+            self.coordinates(steps[0].to).unwrap(),
+        );
+        instructions.push(Instruction {
+            maneuver: Maneuver::Depart,
+            node: steps[0].from,
+            road: road(first),
+            distance,
+            duration,
+            text: depart_text(heading, road(first).as_deref()),
+        });
+
+        let mut index = 1;
+        while index < groups.len() {
+            let group = &groups[index];
+            let entry = &steps[group.start];
+            let (mut distance, mut duration) = totals(group);
+
+            let after_roundabout = groups
+                .get(index + 1)
+                .filter(|next| entry.roundabout() && !steps[next.start].roundabout());
+            let (maneuver, road) = match after_roundabout {
+                // the road after the roundabout is part of the same instruction, unless it is
+                // another roundabout
+                Some(next) => {
+                    let (next_distance, next_duration) = totals(next);
+                    distance += next_distance;
+                    duration += next_duration;
+                    index += 1;
+                    (
+                        Maneuver::Roundabout {
+                            exit: self.roundabout_exit(&steps[group.clone()]),
+                        },
+                        road(next),
+                    )
+                }
+                None if entry.roundabout() => (
+                    Maneuver::Roundabout {
+                        exit: self.roundabout_exit(&steps[group.clone()]),
+                    },
+                    road(group),
+                ),
+                None => {
+                    let previous = &steps[group.start - 1];
+                    let angle = turn_angle(
+                        self.coordinates(previous.from).unwrap(),
+                        self.coordinates(entry.from).unwrap(),
+                        self.coordinates(entry.to).unwrap(),
+                    );
+                    (Maneuver::from_angle(angle), road(group))
+                }
+            };
+
+            instructions.push(Instruction {
+                maneuver,
+                node: entry.from,
+                text: maneuver_text(maneuver, road.as_deref()),
+                road,
+                distance,
+                duration,
+            });
+            index += 1;
+        }
+
+        instructions.push(Instruction {
+            maneuver: Maneuver::Arrive,
+            node: steps.last().unwrap().to,
+            road: None,
+            distance: 0.0,
+            duration: 0.0,
+            text: maneuver_text(Maneuver::Arrive, None),
+        });
+        instructions
+    }
+
+    /// Which road leaving the roundabout the steps around it end at, counting every node passed
+    /// with an edge leaving the roundabout
+    fn roundabout_exit(&self, steps: &[Step]) -> usize {
+        let passed = steps[..steps.len() - 1]
+            .iter()
+            .enumerate()
+            .filter(|(index, step)| {
+                let next = steps[index + 1].to;
+                self.adj_list[&step.to].2.iter().any(|edge| {
+                    let on_roundabout = edge
+                        .way_id
+                        .and_then(|id| self.ways.get(&id))
+                        .is_some_and(|way| way.roundabout);
+                    edge.to != next && edge.to != step.from && !on_roundabout
+                })
+            });
+        passed.count() + 1
+    }
+}
+
+fn depart_text(heading: f64, road: Option<&str>) -> String {
+    const COMPASS: [&str; 8] = [
+        "north",
+        "northeast",
+        "east",
+        "southeast",
+        "south",
+        "southwest",
+        "west",
+        "northwest",
+    ];
+    let direction = COMPASS[((heading.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8];
+    match road {
+        Some(road) => format!("Head {} on {}", direction, road),
+        None => format!("Head {}", direction),
+    }
+}
+
+fn maneuver_text(maneuver: Maneuver, road: Option<&str>) -> String {
+    let action = match maneuver {
+        Maneuver::Depart => "Depart".to_string(),
+        Maneuver::Continue => "Continue".to_string(),
+        Maneuver::SlightLeft => "Turn slightly left".to_string(),
+        Maneuver::Left => "Turn left".to_string(),
+        Maneuver::SharpLeft => "Turn sharp left".to_string(),
+        Maneuver::SlightRight => "Turn slightly right".to_string(),
+        Maneuver::Right => "Turn right".to_string(),
+        Maneuver::SharpRight => "Turn sharp right".to_string(),
+        Maneuver::UTurn => "Make a U-turn".to_string(),
+        Maneuver::Roundabout { exit } => {
+            format!("At the roundabout take the {} exit", ordinal(exit))
+        }
+        Maneuver::Arrive => return "Arrive at your destination".to_string(),
+    };
+    match road {
+        Some(road) => format!("{} onto {}", action, road),
+        None => action,
+    }
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ordinal, Maneuver};
+    use crate::{
+        graph::{
+            tests::{osm_data_at, sample_graph, way},
+            Graph,
+        },
+        search::SearchOptions,
+    };
+
+    /// Westgate runs east in two ways from 1 to 3, where Broyle Road goes north to 4 and Orchard
+    /// Street south to 5
+    fn junction() -> Graph {
+        let data = osm_data_at(
+            &[
+                (1, 50.8365, -0.79),
+                (2, 50.8365, -0.785),
+                (3, 50.8365, -0.78),
+                (4, 50.84, -0.78),
+                (5, 50.833, -0.781),
+            ],
+            vec![
+                way(10, &[1, 2], &[("highway", "primary"), ("name", "Westgate")]),
+                way(11, &[2, 3], &[("highway", "primary"), ("name", "Westgate")]),
+                way(
+                    12,
+                    &[3, 4],
+                    &[
+                        ("highway", "secondary"),
+                        ("name", "Broyle Road"),
+                        ("ref", "B2178"),
+                    ],
+                ),
+                way(
+                    13,
+                    &[3, 5],
+                    &[("highway", "residential"), ("name", "Orchard Street")],
+                ),
+            ],
+            Vec::new(),
+        );
+        Graph::from_osm_data(&data).unwrap()
+    }
+
+    /// A roundabout around (50.83, -0.78) entered from South Street, with East, North and West
+    /// Streets leaving it
+    fn roundabout() -> Graph {
+        let data = osm_data_at(
+            &[
+                (1, 50.829, -0.78),
+                (2, 50.83, -0.7785),
+                (3, 50.831, -0.78),
+                (4, 50.83, -0.7815),
+                (11, 50.825, -0.78),
+                (12, 50.83, -0.77),
+                (13, 50.835, -0.78),
+                (14, 50.83, -0.79),
+            ],
+            vec![
+                way(
+                    20,
+                    &[1, 2, 3, 4, 1],
+                    &[("highway", "primary"), ("junction", "roundabout")],
+                ),
+                way(
+                    21,
+                    &[11, 1],
+                    &[("highway", "primary"), ("name", "South Street")],
+                ),
+                way(
+                    22,
+                    &[2, 12],
+                    &[("highway", "primary"), ("name", "East Street")],
+                ),
+                way(
+                    23,
+                    &[3, 13],
+                    &[("highway", "primary"), ("name", "North Street")],
+                ),
+                way(
+                    24,
+                    &[4, 14],
+                    &[("highway", "primary"), ("name", "West Street")],
+                ),
+            ],
+            Vec::new(),
+        );
+        Graph::from_osm_data(&data).unwrap()
+    }
+
+    fn maneuvers(graph: &Graph, start: u64, end: u64) -> Vec<Maneuver> {
+        let directions = graph
+            .directions(start, end, &SearchOptions::default())
+            .unwrap();
+        directions
+            .instructions
+            .iter()
+            .map(|instruction| instruction.maneuver)
+            .collect()
+    }
+
+    #[test]
+    fn test_instructions_group_by_road() {
+        let graph = junction();
+
+        let directions = graph.directions(1, 4, &SearchOptions::default()).unwrap();
+        let instructions = &directions.instructions;
+
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].text, "Head east on Westgate");
+        // both Westgate ways, 0.0100 degrees of longitude
+        assert!((instructions[0].distance - 703.0).abs() < 10.0);
+        assert_eq!(instructions[1].maneuver, Maneuver::Left);
+        assert_eq!(instructions[1].node, 3);
+        assert_eq!(instructions[1].text, "Turn left onto Broyle Road (B2178)");
+        assert_eq!(instructions[2].text, "Arrive at your destination");
+        let total: f32 = instructions
+            .iter()
+            .map(|instruction| instruction.distance)
+            .sum();
+        assert!((total - directions.route.distance).abs() < 0.1);
+        assert!(instructions
+            .iter()
+            .all(|instruction| instruction.duration >= 0.0));
+    }
+
+    #[test]
+    fn test_instructions_turns() {
+        let graph = junction();
+
+        assert_eq!(
+            maneuvers(&graph, 1, 5),
+            vec![Maneuver::Depart, Maneuver::Right, Maneuver::Arrive]
+        );
+        assert_eq!(
+            maneuvers(&graph, 4, 5),
+            vec![Maneuver::Depart, Maneuver::Continue, Maneuver::Arrive]
+        );
+        assert_eq!(
+            maneuvers(&graph, 4, 1),
+            vec![Maneuver::Depart, Maneuver::Right, Maneuver::Arrive]
+        );
+        assert_eq!(
+            maneuvers(&graph, 2, 3),
+            vec![Maneuver::Depart, Maneuver::Arrive]
+        );
+        assert!(maneuvers(&graph, 1, 1).is_empty());
+    }
+
+    #[test]
+    fn test_roundabout_exits() {
+        let graph = roundabout();
+
+        assert_eq!(
+            maneuvers(&graph, 11, 12),
+            vec![
+                Maneuver::Depart,
+                Maneuver::Roundabout { exit: 1 },
+                Maneuver::Arrive
+            ]
+        );
+        assert_eq!(
+            maneuvers(&graph, 11, 14),
+            vec![
+                Maneuver::Depart,
+                Maneuver::Roundabout { exit: 3 },
+                Maneuver::Arrive
+            ]
+        );
+
+        let directions = graph.directions(11, 13, &SearchOptions::default()).unwrap();
+        let roundabout = &directions.instructions[1];
+        assert_eq!(roundabout.maneuver, Maneuver::Roundabout { exit: 2 });
+        assert_eq!(roundabout.node, 1);
+        assert_eq!(
+            roundabout.text,
+            "At the roundabout take the 2nd exit onto North Street"
+        );
+        // around the roundabout and up North Street
+        assert!(roundabout.distance > directions.instructions[0].distance);
+    }
+
+    #[test]
+    fn test_consecutive_roundabouts() {
+        // a second roundabout east of the first one, sharing its east node, with East Street
+        // leaving it
+        let data = osm_data_at(
+            &[
+                (1, 50.829, -0.78),
+                (2, 50.83, -0.7785),
+                (3, 50.831, -0.78),
+                (4, 50.83, -0.7815),
+                (5, 50.829, -0.777),
+                (6, 50.83, -0.7755),
+                (7, 50.831, -0.777),
+                (11, 50.825, -0.78),
+                (16, 50.83, -0.77),
+            ],
+            vec![
+                way(
+                    20,
+                    &[1, 2, 3, 4, 1],
+                    &[("highway", "primary"), ("junction", "roundabout")],
+                ),
+                way(
+                    30,
+                    &[2, 5, 6, 7, 2],
+                    &[("highway", "primary"), ("junction", "roundabout")],
+                ),
+                way(
+                    21,
+                    &[11, 1],
+                    &[("highway", "primary"), ("name", "South Street")],
+                ),
+                way(
+                    31,
+                    &[6, 16],
+                    &[("highway", "primary"), ("name", "East Street")],
+                ),
+            ],
+            Vec::new(),
+        );
+        let graph = Graph::from_osm_data(&data).unwrap();
+
+        let directions = graph.directions(11, 16, &SearchOptions::default()).unwrap();
+        let instructions = &directions.instructions;
+
+        let maneuvers: Vec<Maneuver> = instructions
+            .iter()
+            .map(|instruction| instruction.maneuver)
+            .collect();
+        assert_eq!(
+            maneuvers,
+            vec![
+                Maneuver::Depart,
+                Maneuver::Roundabout { exit: 1 },
+                Maneuver::Roundabout { exit: 1 },
+                Maneuver::Arrive
+            ]
+        );
+        assert_eq!(instructions[1].node, 1);
+        assert_eq!(instructions[2].node, 2);
+        assert_eq!(instructions[2].road.as_deref(), Some("East Street"));
+        let total: f32 = instructions
+            .iter()
+            .map(|instruction| instruction.distance)
+            .sum();
+        assert!((total - directions.route.distance).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_instructions_without_ways() {
+        let graph = sample_graph();
+
+        let directions = graph.directions(1, 3, &SearchOptions::default()).unwrap();
+
+        assert!(directions
+            .instructions
+            .iter()
+            .all(|instruction| instruction.road.is_none()));
+        assert!(directions.instructions[0].text.starts_with("Head "));
+        assert_eq!(
+            directions.instructions.last().unwrap().maneuver,
+            Maneuver::Arrive
+        );
+    }
+
+    #[test]
+    fn test_ordinal() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 101]
+            .into_iter()
+            .map(ordinal)
+            .collect();
+        assert_eq!(
+            ordinals,
+            vec!["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "101st"]
+        );
+    }
+}
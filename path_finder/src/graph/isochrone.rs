@@ -0,0 +1,397 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::Graph;
+use crate::{
+    error::PathError,
+    hull::{concave_hull, convex_hull, HullKind},
+    metric::Metric,
+    search::IsochroneOptions,
+};
+
+/// A node reachable from the origin of an isochrone
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReachableNode {
+    pub id: u64,
+    pub lat: f64,
+    pub lon: f64,
+    pub cost: f32,
+}
+
+/// The nodes reachable within one threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IsochroneBand {
+    pub threshold: f32,
+    /// How many of the cheapest nodes of the isochrone are within the threshold
+    pub node_count: usize,
+    /// `(lat, lon)` outline around those nodes, counter clockwise and not closed
+    pub hull: Option<Vec<(f64, f64)>>,
+}
+
+/// Everything reachable from a node within the highest threshold, banded by every threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Isochrone {
+    pub origin: u64,
+    pub metric: Metric,
+    /// Cheapest first
+    pub nodes: Vec<ReachableNode>,
+    /// Lowest threshold first, each band holds the ones before it
+    pub bands: Vec<IsochroneBand>,
+}
+
+impl Isochrone {
+    /// The nodes reachable within the band at `index`
+    pub fn band_nodes(&self, index: usize) -> &[ReachableNode] {
+        self.bands
+            .get(index)
+            .map_or(&[], |band| &self.nodes[..band.node_count])
+    }
+
+    /// A GeoJSON `FeatureCollection` with a polygon per band that has a hull of at least 3 points,
+    /// highest threshold first so the smaller bands are drawn on top
+    pub fn to_geojson(&self) -> Value {
+        let features: Vec<Value> = self
+            .bands
+            .iter()
+            .rev()
+            .filter_map(|band| {
+                let hull = band.hull.as_ref().filter(|hull| hull.len() >= 3)?;
+                let ring: Vec<[f64; 2]> = hull
+                    .iter()
+                    .chain(hull.first())
+                    .map(|&(lat, lon)| [lon, lat])
+                    .collect();
+                Some(json!({
+                    "type": "Feature",
+                    "properties": {
+                        "threshold": band.threshold,
+                        "metric": self.metric,
+                        "nodes": band.node_count,
+                    },
+                    "geometry": { "type": "Polygon", "coordinates": [ring] },
+                }))
+            })
+            .collect();
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+}
+
+impl Graph {
+    /// Isochrone around the node nearest to the `(lat, lon)` point
+    pub fn isochrone_from_point(
+        &self,
+        lat: f64,
+        lon: f64,
+        options: &IsochroneOptions,
+    ) -> Result<Isochrone, PathError> {
+        let origin = self
+            .nearest_neighbor(lat, lon)
+            .ok_or(PathError::NoNearbyNode(0))?;
+        self.isochrone(origin, options)
+    }
+
+    /// Every node reachable from the origin within the highest threshold of the options, with its
+    /// cost, and a band with an optional outline for each threshold
+    /// Turn restrictions and turn costs are not taken into account
+    pub fn isochrone(
+        &self,
+        origin: u64,
+        options: &IsochroneOptions,
+    ) -> Result<Isochrone, PathError> {
+        if let Some(&threshold) = options
+            .thresholds
+            .iter()
+            .find(|threshold| threshold.is_nan() || **threshold <= 0.0)
+        {
+            return Err(PathError::InvalidThreshold(threshold));
+        }
+        let mut thresholds = options.thresholds.clone();
+        thresholds.sort_by(f32::total_cmp);
+        thresholds.dedup();
+        let max_cost = *thresholds.last().ok_or(PathError::NoThresholds)?;
+
+        let mut nodes: Vec<ReachableNode> = self
+            .costs_within(origin, max_cost, options.metric)
+            .into_iter()
+            .map(|(id, cost)| {
+                let (lat, lon, _) = self.adj_list[&id];
+                ReachableNode { id, lat, lon, cost }
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.cost.total_cmp(&b.cost).then(a.id.cmp(&b.id)));
+
+        let bands = thresholds
+            .into_iter()
+            .map(|threshold| {
+                let node_count = nodes.partition_point(|node| node.cost <= threshold);
+                let points: Vec<(f64, f64)> = nodes[..node_count]
+                    .iter()
+                    .map(|node| (node.lat, node.lon))
+                    .collect();
+                let hull = match options.hull {
+                    HullKind::None => None,
+                    HullKind::Convex => Some(convex_hull(&points)),
+                    HullKind::Concave => Some(concave_hull(&points, options.concavity)),
+                };
+                IsochroneBand {
+                    threshold,
+                    node_count,
+                    hull,
+                }
+            })
+            .collect();
+
+        Ok(Isochrone {
+            origin,
+            metric: options.metric,
+            nodes,
+            bands,
+        })
+    }
+
+    /// Dijkstra from the source that stops at the first node costing more than `max_cost`
+    /// The shortest metric sums the lengths of the edges rather than their weights, so the profile
+    /// doesn't stretch the area
+    fn costs_within(&self, source: u64, max_cost: f32, metric: Metric) -> HashMap<u64, f32> {
+        let mut costs: HashMap<u64, f32> = HashMap::new();
+        let mut settled: HashMap<u64, f32> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>> = BinaryHeap::new();
+
+        if self.contains_node_id(source) {
+            costs.insert(source, 0.0);
+            heap.push(Reverse((OrderedFloat(0.0), source)));
+        }
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            let cost = cost.into_inner();
+            if cost > max_cost {
+                break;
+            }
+            if cost > costs[&node] {
+                continue;
+            }
+            settled.insert(node, cost);
+
+            for edge in &self.adj_list[&node].2 {
+                let edge_cost = match metric {
+                    Metric::Shortest => edge.distance,
+                    Metric::Fastest => edge.duration,
+                };
+                let new_cost = cost + edge_cost;
+                if new_cost < *costs.get(&edge.to).unwrap_or(&f32::MAX) {
+                    costs.insert(edge.to, new_cost);
+                    heap.push(Reverse((OrderedFloat(new_cost), edge.to)));
+                }
+            }
+        }
+
+        settled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::PathError,
+        graph::{
+            tests::{osm_data_at, path_cost, random_graph, sample_graph, way},
+            Graph,
+        },
+        hull::HullKind,
+        metric::Metric,
+        profile::Profile,
+        search::IsochroneOptions,
+    };
+
+    fn options(thresholds: &[f32]) -> IsochroneOptions {
+        IsochroneOptions {
+            thresholds: thresholds.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_isochrone_matches_dijkstra() {
+        for seed in 0..3 {
+            let graph = random_graph(seed, 60, 200);
+            let threshold = 5.0;
+
+            let isochrone = graph.isochrone(0, &options(&[threshold])).unwrap();
+
+            for id in 0..60 {
+                let path = graph.find_shortest_path(0, id);
+                let expected = (!path.is_empty())
+                    .then(|| path_cost(&graph, &path))
+                    .filter(|&cost| cost <= threshold);
+                let found = isochrone.nodes.iter().find(|node| node.id == id);
+                match (found, expected) {
+                    (Some(node), Some(cost)) => assert!((node.cost - cost).abs() < 1e-3),
+                    (None, None) => {}
+                    // right at the threshold rounding can go either way
+                    (found, expected) => {
+                        let cost = found.map(|node| node.cost).or(expected).unwrap();
+                        assert!(
+                            (cost - threshold).abs() < 1e-3,
+                            "seed {} node {}: {:?} {:?}",
+                            seed,
+                            id,
+                            found,
+                            expected
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_isochrone_bands() {
+        let graph = sample_graph();
+
+        let isochrone = graph.isochrone(1, &options(&[12.0, 2.0, 6.0])).unwrap();
+
+        let thresholds: Vec<f32> = isochrone.bands.iter().map(|band| band.threshold).collect();
+        assert_eq!(thresholds, vec![2.0, 6.0, 12.0]);
+        assert_eq!(isochrone.nodes[0].id, 1);
+        assert_eq!(isochrone.nodes[0].cost, 0.0);
+        for (index, band) in isochrone.bands.iter().enumerate() {
+            let nodes = isochrone.band_nodes(index);
+            assert_eq!(nodes.len(), band.node_count);
+            assert!(nodes.iter().all(|node| node.cost <= band.threshold));
+            assert!(isochrone.nodes[band.node_count..]
+                .iter()
+                .all(|node| node.cost > band.threshold));
+        }
+        // 1 -> 4 is 1.92km
+        assert_eq!(
+            isochrone
+                .band_nodes(0)
+                .iter()
+                .map(|node| node.id)
+                .collect::<Vec<_>>(),
+            vec![1, 4]
+        );
+    }
+
+    #[test]
+    fn test_isochrone_fastest() {
+        let graph = sample_graph();
+
+        let options = IsochroneOptions {
+            metric: Metric::Fastest,
+            thresholds: vec![300.0],
+            ..Default::default()
+        };
+        let isochrone = graph.isochrone(1, &options).unwrap();
+
+        assert_eq!(isochrone.metric, Metric::Fastest);
+        assert!(isochrone.nodes.iter().all(|node| node.cost <= 300.0));
+        assert!(isochrone.nodes.iter().any(|node| node.id == 4));
+    }
+
+    #[test]
+    fn test_isochrone_distance_ignores_cost_factors() {
+        // a service road with a stop every 500m, which the car profile weighs at 1.5 times its
+        // length
+        let data = osm_data_at(
+            &[(1, 50.0, 0.0), (2, 50.0, 0.007), (3, 50.0, 0.014)],
+            vec![way(100, &[1, 2, 3], &[("highway", "service")])],
+            Vec::new(),
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        let isochrone = graph.isochrone(1, &options(&[1.2])).unwrap();
+
+        let ids: Vec<u64> = isochrone.nodes.iter().map(|node| node.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        let length = Graph::calculate_distance((50.0, 0.0), (50.0, 0.007)) * 2.0;
+        assert!((isochrone.nodes[2].cost - length).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_isochrone_hulls() {
+        let graph = random_graph(7, 80, 300);
+
+        for hull in [HullKind::Convex, HullKind::Concave] {
+            let options = IsochroneOptions {
+                thresholds: vec![3.0, 8.0],
+                hull,
+                ..Default::default()
+            };
+            let isochrone = graph.isochrone(0, &options).unwrap();
+
+            for (index, band) in isochrone.bands.iter().enumerate() {
+                let outline = band.hull.as_ref().unwrap();
+                assert!(outline.len() <= band.node_count);
+                let nodes = isochrone.band_nodes(index);
+                assert!(outline.iter().all(|&(lat, lon)| nodes
+                    .iter()
+                    .any(|node| (node.lat, node.lon) == (lat, lon))));
+            }
+        }
+
+        let none = IsochroneOptions {
+            thresholds: vec![3.0],
+            hull: HullKind::None,
+            ..Default::default()
+        };
+        assert!(graph.isochrone(0, &none).unwrap().bands[0].hull.is_none());
+    }
+
+    #[test]
+    fn test_isochrone_geojson() {
+        let mut graph = Graph::new();
+        graph.add_edge_two_way((1, 50.0, 0.0), (2, 50.0, 0.01));
+        graph.add_edge_two_way((1, 50.0, 0.0), (3, 50.01, 0.0));
+        graph.add_edge_two_way((2, 50.0, 0.01), (4, 50.01, 0.01));
+
+        let isochrone = graph.isochrone(1, &options(&[0.5, 10.0])).unwrap();
+        let geojson = isochrone.to_geojson();
+
+        assert_eq!(geojson["type"], "FeatureCollection");
+        // only the node itself is within 500m, too few for a polygon
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0]["properties"]["threshold"], 10.0);
+        let ring = features[0]["geometry"]["coordinates"][0]
+            .as_array()
+            .unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring.first(), ring.last());
+        assert_eq!(ring[0], serde_json::json!([0.0, 50.0]));
+    }
+
+    #[test]
+    fn test_isochrone_invalid_thresholds() {
+        let graph = sample_graph();
+
+        assert_eq!(
+            graph.isochrone(1, &options(&[])),
+            Err(PathError::NoThresholds)
+        );
+        assert_eq!(
+            graph.isochrone(1, &options(&[5.0, -1.0])),
+            Err(PathError::InvalidThreshold(-1.0))
+        );
+        assert!(matches!(
+            graph.isochrone(1, &options(&[f32::NAN])),
+            Err(PathError::InvalidThreshold(_))
+        ));
+        assert!(graph
+            .isochrone(42, &options(&[5.0]))
+            .unwrap()
+            .nodes
+            .is_empty());
+        assert_eq!(
+            Graph::new().isochrone_from_point(50.0, 0.0, &options(&[5.0])),
+            Err(PathError::NoNearbyNode(0))
+        );
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use ordered_float::OrderedFloat;
+
+use super::{turn_aware::Arrival, Graph, Route};
+use crate::{
+    metric::Metric,
+    search::{AlternativeOptions, MAX_CANDIDATES},
+};
+
+impl Graph {
+    /// Finds up to `k` loopless paths from the start to the end, cheapest first, using Yen's
+    /// algorithm
+    /// Paths never take a turn forbidden by the restrictions of the graph, see `yen` for what
+    /// loopless means then
+    pub fn k_shortest_paths(&self, start: u64, end: u64, k: usize) -> Vec<Route> {
+        self.k_shortest_paths_with(start, end, k, Metric::Shortest)
+    }
+
+    /// Same as `k_shortest_paths` but minimising the metric
+    pub fn k_shortest_paths_with(
+        &self,
+        start: u64,
+        end: u64,
+        k: usize,
+        metric: Metric,
+    ) -> Vec<Route> {
+        self.yen(start, end, metric, !self.restrictions.is_empty())
+            .take(k)
+            .map(|(nodes, _)| self.to_route(&self.with_coordinates(&nodes), metric))
+            .collect()
+    }
+
+    /// The shortest path followed by up to `count` alternatives, each sharing at most `max_overlap`
+    /// of its length with the paths before it, so they are meaningfully different routes
+    /// Only the first `candidates` paths of Yen's algorithm are considered, which follow the turn
+    /// restrictions unless `ignore_turn_restrictions` is set
+    pub fn alternative_routes(
+        &self,
+        start: u64,
+        end: u64,
+        options: &AlternativeOptions,
+    ) -> Vec<Route> {
+        let metric = options.metric;
+        let restricted = !options.ignore_turn_restrictions && !self.restrictions.is_empty();
+        let mut routes: Vec<Vec<u64>> = Vec::new();
+
+        for (nodes, _) in self
+            .yen(start, end, metric, restricted)
+            .take(options.candidates.min(MAX_CANDIDATES))
+        {
+            if routes
+                .iter()
+                .all(|route| self.overlap(&nodes, route, metric) <= options.max_overlap)
+            {
+                routes.push(nodes);
+                if routes.len() > options.count {
+                    break;
+                }
+            }
+        }
+
+        routes
+            .into_iter()
+            .map(|nodes| self.to_route(&self.with_coordinates(&nodes), metric))
+            .collect()
+    }
+
+    /// Lazily yields the loopless paths from the start to the end as node ids, cheapest first
+    /// When `restricted` the paths follow the turn restrictions and are searched over nodes
+    /// together with the way they are entered by, so a path may pass a node twice from different
+    /// ways, like going round the block after a forbidden turn, and the first one is the route of
+    /// the edge based search
+    fn yen(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        restricted: bool,
+    ) -> impl Iterator<Item = (Vec<u64>, f32)> + '_ {
+        let mut found: Vec<Vec<Arrival>> = Vec::new();
+        let mut candidates: BinaryHeap<Reverse<(OrderedFloat<f32>, Vec<Arrival>)>> =
+            BinaryHeap::new();
+        let mut seen: HashSet<Vec<Arrival>> = HashSet::new();
+
+        let no_states = HashSet::new();
+        if let Some((path, cost)) = self.restricted_dijkstra(
+            (start, None),
+            end,
+            metric,
+            restricted,
+            &no_states,
+            &HashSet::new(),
+        ) {
+            seen.insert(path.clone());
+            candidates.push(Reverse((OrderedFloat(cost), path)));
+        }
+
+        std::iter::from_fn(move || {
+            let Reverse((cost, path)) = candidates.pop()?;
+
+            // every state of the new path but the end is a spur the next candidates branch off from
+            let mut root_cost = 0.0;
+            for i in 0..path.len() - 1 {
+                let (root, spur) = (&path[..=i], path[i]);
+
+                // the next step of every path sharing this root is taken already
+                let banned_steps: HashSet<(Arrival, Arrival)> = found
+                    .iter()
+                    .chain(std::iter::once(&path))
+                    .filter(|other| other.len() > i + 1 && other[..=i] == *root)
+                    .map(|other| (other[i], other[i + 1]))
+                    .collect();
+                // and going back through the root would make a loop
+                let banned_states: HashSet<Arrival> = root[..i].iter().copied().collect();
+
+                if let Some((spur_path, spur_cost)) = self.restricted_dijkstra(
+                    spur,
+                    end,
+                    metric,
+                    restricted,
+                    &banned_states,
+                    &banned_steps,
+                ) {
+                    let candidate: Vec<Arrival> =
+                        root[..i].iter().chain(&spur_path).copied().collect();
+                    if seen.insert(candidate.clone()) {
+                        candidates.push(Reverse((OrderedFloat(root_cost + spur_cost), candidate)));
+                    }
+                }
+
+                root_cost += self
+                    .cheapest_edge(path[i].0, path[i + 1].0, metric)
+                    .map_or(0.0, |edge| edge.cost(metric));
+            }
+
+            found.push(path.clone());
+            let nodes = path.iter().map(|&(node, _)| node).collect();
+            Some((nodes, cost.into_inner()))
+        })
+    }
+
+    /// Dijkstra from the state to the first state at `end` that never enters `banned_states`
+    /// nor takes `banned_steps`, returns the states and cost
+    /// Only when `restricted` are the ways part of the states and the turn restrictions followed
+    fn restricted_dijkstra(
+        &self,
+        start: Arrival,
+        end: u64,
+        metric: Metric,
+        restricted: bool,
+        banned_states: &HashSet<Arrival>,
+        banned_steps: &HashSet<(Arrival, Arrival)>,
+    ) -> Option<(Vec<Arrival>, f32)> {
+        let mut distances: HashMap<Arrival, f32> = HashMap::new();
+        let mut predecessors: HashMap<Arrival, Arrival> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, Arrival)>> = BinaryHeap::new();
+
+        self.adj_list.get(&end)?;
+        distances.insert(start, 0.0);
+        heap.push(Reverse((OrderedFloat(0.0), start)));
+
+        while let Some(Reverse((cost, state))) = heap.pop() {
+            let cost = cost.into_inner();
+            let (node, incoming_way) = state;
+            if cost > distances[&state] {
+                continue;
+            }
+            if node == end {
+                let mut path = vec![state];
+                while let Some(&prev) = predecessors.get(path.last().unwrap()) {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some((path, cost));
+            }
+
+            for edge in self
+                .adj_list
+                .get(&node)
+                .into_iter()
+                .flat_map(|(_, _, edges)| edges)
+            {
+                if restricted && !self.is_turn_allowed(node, incoming_way, edge.way_id) {
+                    continue;
+                }
+                let next = (edge.to, edge.way_id.filter(|_| restricted));
+                if banned_states.contains(&next) || banned_steps.contains(&(state, next)) {
+                    continue;
+                }
+                let new_cost = cost + edge.cost(metric);
+                if new_cost < *distances.get(&next).unwrap_or(&f32::MAX) {
+                    distances.insert(next, new_cost);
+                    predecessors.insert(next, state);
+                    heap.push(Reverse((OrderedFloat(new_cost), next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Share of the length of `path` that is also on `other`, by edge distance
+    fn overlap(&self, path: &[u64], other: &[u64], metric: Metric) -> f64 {
+        let shared: HashSet<(u64, u64)> = other.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        let (mut total, mut overlapping) = (0.0, 0.0);
+
+        for pair in path.windows(2) {
+            let distance = self
+                .cheapest_edge(pair[0], pair[1], metric)
+                .map_or(0.0, |edge| edge.distance as f64);
+            total += distance;
+            if shared.contains(&(pair[0], pair[1])) {
+                overlapping += distance;
+            }
+        }
+
+        if total > 0.0 {
+            overlapping / total
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        graph::{
+            tests::{osm_data_at, path_cost, random_graph, restriction, sample_graph, way},
+            Graph, Route,
+        },
+        profile::Profile,
+        search::{AlternativeOptions, SearchOptions},
+    };
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let graph = sample_graph();
+
+        let paths = graph.k_shortest_paths(1, 5, 3);
+
+        // 1 -> 4 -> 5 and 1 -> 2 -> 5 are the only loopless paths
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[1].node_ids(), vec![1, 2, 5]);
+        assert_eq!(paths[0], graph.find_shortest_path(1, 5));
+        for pair in paths.windows(2) {
+            assert!(pair[0].cost <= pair[1].cost);
+            assert_ne!(pair[0].node_ids(), pair[1].node_ids());
+        }
+        for ranked in &paths {
+            assert!((ranked.cost - path_cost(&graph, ranked)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_k_shortest_paths_are_loopless_and_distinct() {
+        for seed in 0..3 {
+            let graph = random_graph(seed, 40, 120);
+            let paths = graph.k_shortest_paths(0, 39, 6);
+
+            let mut seen = HashSet::new();
+            for ranked in &paths {
+                let nodes = ranked.node_ids();
+                assert_eq!(nodes.first(), Some(&0));
+                assert_eq!(nodes.last(), Some(&39));
+                assert_eq!(
+                    nodes.iter().collect::<HashSet<_>>().len(),
+                    nodes.len(),
+                    "loop in {:?}",
+                    nodes
+                );
+                assert!(seen.insert(nodes));
+            }
+            for pair in paths.windows(2) {
+                assert!(pair[0].cost <= pair[1].cost + 1e-4);
+            }
+            if let Some(first) = paths.first() {
+                assert!(
+                    (first.cost - path_cost(&graph, &graph.find_shortest_path(0, 39))).abs() < 1e-3
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_k_shortest_paths_runs_out() {
+        let mut graph = Graph::new();
+        graph.add_edge_two_way((1, 50.0, 0.0), (2, 50.0, 0.001));
+        graph.add_edge_two_way((2, 50.0, 0.001), (3, 50.0, 0.002));
+
+        assert_eq!(graph.k_shortest_paths(1, 3, 5).len(), 1);
+        assert!(graph.k_shortest_paths(1, 42, 5).is_empty());
+    }
+
+    #[test]
+    fn test_alternative_routes() {
+        // a ladder: two parallel roads from 1 to 4 joined by rungs, so most of Yen's paths share a
+        // lot
+        let mut graph = Graph::new();
+        let top = [
+            (1, 50.0, 0.0),
+            (2, 50.0, 0.01),
+            (3, 50.0, 0.02),
+            (4, 50.0, 0.03),
+        ];
+        let bottom = [
+            (5, 49.999, 0.0),
+            (6, 49.999, 0.01),
+            (7, 49.999, 0.02),
+            (8, 49.999, 0.03),
+        ];
+        for i in 0..3 {
+            graph.add_edge_two_way(top[i], top[i + 1]);
+            graph.add_edge_two_way(bottom[i], bottom[i + 1]);
+        }
+        for i in 0..4 {
+            graph.add_edge_two_way(top[i], bottom[i]);
+        }
+
+        let options = AlternativeOptions {
+            count: 1,
+            max_overlap: 0.2,
+            ..Default::default()
+        };
+        let routes = graph.alternative_routes(1, 4, &options);
+
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].node_ids(), vec![1, 2, 3, 4]);
+        assert_eq!(routes[1].node_ids(), vec![1, 5, 6, 7, 8, 4]);
+
+        // allowing any overlap is just the k shortest paths
+        let options = AlternativeOptions {
+            count: 2,
+            max_overlap: 1.0,
+            ..Default::default()
+        };
+        let routes = graph.alternative_routes(1, 4, &options);
+        let paths = graph.k_shortest_paths(1, 4, 3);
+        assert_eq!(routes, paths);
+    }
+
+    /// Junction 5 with a short arm west to 1, an arm east to 2 and north to 3, where the left turn
+    /// from the west into the north arm is forbidden, and with `loop_road` a road from 2 round to 3
+    fn restricted_junction(loop_road: bool) -> Graph {
+        let mut ways = vec![
+            way(100, &[1, 5], &[("highway", "residential")]),
+            way(101, &[5, 2], &[("highway", "residential")]),
+            way(102, &[3, 5], &[("highway", "residential")]),
+        ];
+        if loop_road {
+            ways.push(way(104, &[2, 6, 3], &[("highway", "residential")]));
+        }
+        let data = osm_data_at(
+            &[
+                (5, 50.8365, -0.78),
+                (1, 50.8365, -0.7814),
+                (2, 50.8365, -0.7772),
+                (3, 50.8374, -0.78),
+                (6, 50.8383, -0.7772),
+            ],
+            ways,
+            vec![restriction(1, "no_left_turn", 100, 5, 102)],
+        );
+        Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap()
+    }
+
+    #[test]
+    fn test_alternatives_follow_restrictions() {
+        let graph = restricted_junction(true);
+        let ids = |routes: &[Route]| {
+            routes
+                .iter()
+                .map(|route| route.node_ids())
+                .collect::<Vec<_>>()
+        };
+
+        // turning back at 2 is a bit shorter than the loop
+        let paths = graph.k_shortest_paths(1, 3, 2);
+        assert_eq!(ids(&paths), vec![vec![1, 5, 2, 5, 3], vec![1, 5, 2, 6, 3]]);
+        assert_eq!(
+            paths[0],
+            graph.search(1, 3, &SearchOptions::default()).unwrap().route
+        );
+
+        let options = AlternativeOptions {
+            max_overlap: 1.0,
+            ..Default::default()
+        };
+        let routes = graph.alternative_routes(1, 3, &options);
+        assert_eq!(routes[0], paths[0]);
+        assert!(routes
+            .iter()
+            .all(|route| route.node_ids().windows(3).all(|turn| turn != [1, 5, 3])));
+
+        let ignoring = AlternativeOptions {
+            ignore_turn_restrictions: true,
+            ..options
+        };
+        let routes = graph.alternative_routes(1, 3, &ignoring);
+        assert_eq!(ids(&routes), vec![vec![1, 5, 3], vec![1, 5, 2, 6, 3]]);
+    }
+
+    #[test]
+    fn test_alternatives_revisit_the_via_node() {
+        // without the loop the only legal way into the north arm is turning back at 2 and through 5
+        // again
+        let graph = restricted_junction(false);
+
+        let paths = graph.k_shortest_paths(1, 3, 3);
+
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].node_ids(), vec![1, 5, 2, 5, 3]);
+        assert_eq!(
+            paths[0],
+            graph.search(1, 3, &SearchOptions::default()).unwrap().route
+        );
+        let routes = graph.alternative_routes(1, 3, &AlternativeOptions::default());
+        assert_eq!(routes, paths);
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use super::{Graph, Route};
+use crate::{error::PathError, metric::Metric, trace::Tracer};
+
+/// How the landmarks of `ALT` are picked
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LandmarkStrategy {
+    /// Each landmark is the node furthest from the ones already picked
+    Farthest,
+    /// Each landmark is a leaf of a shortest path tree under the part of the tree the others cover
+    /// worst
+    #[default]
+    Avoid,
+}
+
+impl FromStr for LandmarkStrategy {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "farthest" => Ok(LandmarkStrategy::Farthest),
+            "avoid" => Ok(LandmarkStrategy::Avoid),
+            other => Err(PathError::UnknownLandmarkStrategy(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LandmarkStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LandmarkStrategy::Farthest => "farthest",
+            LandmarkStrategy::Avoid => "avoid",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Costs to and from a few landmark nodes for one metric
+/// By the triangle inequality they give a lower bound on the cost between any two nodes,
+/// which is much tighter than the `Haversine` distance when edges are weighted by time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Landmarks {
+    metric: Metric,
+    nodes: Vec<u64>,
+    /// Cost from each landmark to every node it reaches
+    from_landmark: Vec<HashMap<u64, f32>>,
+    /// Cost to each landmark from every node that reaches it
+    to_landmark: Vec<HashMap<u64, f32>>,
+}
+
+impl Landmarks {
+    /// Picks `count` landmarks with the strategy and computes their cost tables
+    pub fn new(graph: &Graph, count: usize, strategy: LandmarkStrategy, metric: Metric) -> Self {
+        let mut landmarks = Landmarks {
+            metric,
+            nodes: Vec::new(),
+            from_landmark: Vec::new(),
+            to_landmark: Vec::new(),
+        };
+
+        let mut nodes: Vec<u64> = graph.adj_list.keys().copied().collect();
+        nodes.sort_unstable();
+
+        for round in 0..count.min(nodes.len()) {
+            let picked = match strategy {
+                LandmarkStrategy::Farthest => landmarks.farthest(graph, &nodes),
+                // the roots are spread over the nodes instead of always growing the same tree
+                LandmarkStrategy::Avoid => landmarks
+                    .avoid(graph, nodes[round * 7919 % nodes.len()])
+                    .or_else(|| landmarks.farthest(graph, &nodes)),
+            };
+            let Some(node) = picked else {
+                break;
+            };
+
+            landmarks.nodes.push(node);
+            landmarks
+                .from_landmark
+                .push(graph.costs_from(node, metric, false).0);
+            landmarks
+                .to_landmark
+                .push(graph.costs_from(node, metric, true).0);
+        }
+
+        landmarks
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    pub fn nodes(&self) -> &[u64] {
+        &self.nodes
+    }
+
+    /// Lower bound on the cost from `node` to `end`, 0 when no landmark reaches both
+    pub fn lower_bound(&self, node: u64, end: u64) -> f32 {
+        let mut bound = 0.0f32;
+        for (from, to) in self.from_landmark.iter().zip(&self.to_landmark) {
+            // landmark -> node -> end can't be cheaper than landmark -> end
+            if let (Some(l_end), Some(l_node)) = (from.get(&end), from.get(&node)) {
+                bound = bound.max(l_end - l_node);
+            }
+            // node -> end -> landmark can't be cheaper than node -> landmark
+            if let (Some(node_l), Some(end_l)) = (to.get(&node), to.get(&end)) {
+                bound = bound.max(node_l - end_l);
+            }
+        }
+        bound
+    }
+
+    /// The node with the highest cost from its closest landmark, nodes no landmark reaches come
+    /// first
+    /// With no landmarks yet it is the node furthest from the first node
+    fn farthest(&self, graph: &Graph, nodes: &[u64]) -> Option<u64> {
+        let first;
+        let tables = if self.from_landmark.is_empty() {
+            first = [graph.costs_from(*nodes.first()?, self.metric, false).0];
+            &first[..]
+        } else {
+            &self.from_landmark[..]
+        };
+
+        let picked: HashSet<u64> = self.nodes.iter().copied().collect();
+        nodes
+            .iter()
+            .copied()
+            .filter(|node| !picked.contains(node))
+            .max_by_key(|node| {
+                let closest = tables
+                    .iter()
+                    .map(|table| *table.get(node).unwrap_or(&f32::MAX))
+                    .fold(f32::MAX, f32::min);
+                (OrderedFloat(closest), Reverse(*node))
+            })
+    }
+
+    /// Grows a shortest path tree from `root` and weighs every node by how far its cost is above
+    /// the current lower bound, ignoring subtrees that already contain a landmark
+    /// Then walks down from the heaviest subtree to a leaf
+    fn avoid(&self, graph: &Graph, root: u64) -> Option<u64> {
+        let (costs, parents) = graph.costs_from(root, self.metric, false);
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (&child, &parent) in &parents {
+            children.entry(parent).or_default().push(child);
+        }
+
+        // parents before their children, walked backwards below
+        let mut order = vec![root];
+        let mut index = 0;
+        while let Some(&node) = order.get(index) {
+            order.extend(children.get(&node).into_iter().flatten());
+            index += 1;
+        }
+
+        let landmarks: HashSet<u64> = self.nodes.iter().copied().collect();
+        let mut sizes: HashMap<u64, f32> = HashMap::new();
+        let mut covered: HashSet<u64> = HashSet::new();
+        for &node in order.iter().rev() {
+            let node_children = children.get(&node).map_or(&[][..], Vec::as_slice);
+            if landmarks.contains(&node)
+                || node_children.iter().any(|child| covered.contains(child))
+            {
+                covered.insert(node);
+                sizes.insert(node, 0.0);
+                continue;
+            }
+
+            let weight = costs[&node] - self.lower_bound(root, node);
+            let size = weight + node_children.iter().map(|child| sizes[child]).sum::<f32>();
+            sizes.insert(node, size);
+        }
+
+        let mut node = order
+            .iter()
+            .copied()
+            .filter(|node| sizes[node] > 0.0)
+            .max_by_key(|node| (OrderedFloat(sizes[node]), Reverse(*node)))?;
+        while let Some(node_children) = children.get(&node) {
+            node = node_children
+                .iter()
+                .copied()
+                .max_by_key(|child| (OrderedFloat(sizes[child]), Reverse(*child)))?;
+        }
+        Some(node)
+    }
+}
+
+impl Graph {
+    /// Picks `count` landmarks and stores their cost tables for `Algorithm::Alt`, replacing any
+    /// earlier ones
+    /// They are serialised with the graph
+    pub fn build_landmarks(&mut self, count: usize, strategy: LandmarkStrategy, metric: Metric) {
+        self.landmarks = Some(Landmarks::new(self, count, strategy, metric));
+    }
+
+    pub fn landmarks(&self) -> Option<&Landmarks> {
+        self.landmarks.as_ref()
+    }
+
+    /// Finds the shortest path using `A*` with the landmark lower bounds,
+    /// which have to be built for the shortest metric
+    pub fn find_shortest_path_alt(&self, start: u64, end: u64) -> Result<Route, PathError> {
+        let path = self.alt(start, end, Metric::Shortest, Tracer::new(self, None))?;
+        Ok(self.to_route(&path, Metric::Shortest))
+    }
+
+    pub(super) fn alt(
+        &self,
+        start: u64,
+        end: u64,
+        metric: Metric,
+        tracer: Tracer,
+    ) -> Result<Vec<(u64, f64, f64)>, PathError> {
+        let landmarks = self.landmarks_for(metric)?;
+        Ok(self.astar_with(
+            start,
+            end,
+            metric,
+            |node| landmarks.lower_bound(node, end),
+            tracer,
+        ))
+    }
+
+    /// The landmarks of the graph when they were built for the metric
+    pub(super) fn landmarks_for(&self, metric: Metric) -> Result<&Landmarks, PathError> {
+        self.landmarks
+            .as_ref()
+            .filter(|landmarks| landmarks.metric == metric)
+            .ok_or(PathError::MissingLandmarks(metric))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LandmarkStrategy;
+    use crate::{
+        algorithm::Algorithm,
+        error::PathError,
+        graph::{
+            tests::{one_way_graph, path_cost, random_graph, sample_graph},
+            Graph,
+        },
+        metric::Metric,
+        search::SearchOptions,
+    };
+
+    const STRATEGIES: [LandmarkStrategy; 2] = [LandmarkStrategy::Farthest, LandmarkStrategy::Avoid];
+
+    #[test]
+    fn test_alt_matches_dijkstra() {
+        for strategy in STRATEGIES {
+            for mut graph in [sample_graph(), one_way_graph()] {
+                graph.build_landmarks(2, strategy, Metric::Shortest);
+                for start in 1..=5 {
+                    for end in 1..=5 {
+                        assert_eq!(
+                            graph.find_shortest_path_alt(start, end),
+                            Ok(graph.find_shortest_path(start, end)),
+                            "paths differ for {} -> {} with {}",
+                            start,
+                            end,
+                            strategy
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_alt_random_graphs() {
+        for strategy in STRATEGIES {
+            for seed in 0..3 {
+                let mut graph = random_graph(seed, 60, 150);
+                graph.build_landmarks(4, strategy, Metric::Shortest);
+                assert_eq!(graph.landmarks().unwrap().nodes().len(), 4);
+
+                for start in (0..60).step_by(3) {
+                    for end in (0..60).step_by(7) {
+                        let expected = graph.find_shortest_path(start, end);
+                        let path = graph.find_shortest_path_alt(start, end).unwrap();
+
+                        assert_eq!(
+                            path.is_empty(),
+                            expected.is_empty(),
+                            "seed {} {} -> {}",
+                            seed,
+                            start,
+                            end
+                        );
+                        assert!(
+                            (path_cost(&graph, &path) - path_cost(&graph, &expected)).abs() < 1e-3,
+                            "seed {} {} -> {}",
+                            seed,
+                            start,
+                            end
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lower_bounds_are_admissible() {
+        let mut graph = random_graph(4, 60, 150);
+        graph.build_landmarks(4, LandmarkStrategy::Avoid, Metric::Shortest);
+        let landmarks = graph.landmarks().unwrap();
+
+        for start in 0..60 {
+            let (costs, _) = graph.costs_from(start, Metric::Shortest, false);
+            for (&end, &cost) in &costs {
+                assert!(
+                    landmarks.lower_bound(start, end) <= cost + 1e-3,
+                    "{} -> {}",
+                    start,
+                    end
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_alt_settles_fewer_nodes() {
+        let settled = |graph: &Graph, algorithm: Algorithm| -> usize {
+            let options = SearchOptions {
+                algorithm,
+                metric: Metric::Fastest,
+                trace: true,
+                ..Default::default()
+            };
+            (0..200)
+                .step_by(11)
+                .flat_map(|start| (0..200).step_by(13).map(move |end| (start, end)))
+                .map(|(start, end)| {
+                    graph
+                        .search(start, end, &options)
+                        .unwrap()
+                        .trace
+                        .unwrap()
+                        .settled
+                        .len()
+                })
+                .sum()
+        };
+
+        for strategy in STRATEGIES {
+            let mut graph = random_graph(5, 200, 600);
+            graph.build_landmarks(8, strategy, Metric::Fastest);
+
+            let dijkstra = settled(&graph, Algorithm::Dijkstra);
+            let alt = settled(&graph, Algorithm::Alt);
+            assert!(
+                alt * 2 < dijkstra,
+                "{} settled {} nodes against {} for dijkstra",
+                strategy,
+                alt,
+                dijkstra
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_landmarks() {
+        let mut graph = sample_graph();
+        let options = SearchOptions {
+            algorithm: Algorithm::Alt,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            graph.search(1, 5, &options),
+            Err(PathError::MissingLandmarks(Metric::Shortest))
+        );
+
+        graph.build_landmarks(2, LandmarkStrategy::Farthest, Metric::Fastest);
+        assert_eq!(
+            graph.search(1, 5, &options),
+            Err(PathError::MissingLandmarks(Metric::Shortest))
+        );
+        let fastest = SearchOptions {
+            metric: Metric::Fastest,
+            ..options
+        };
+        let dijkstra = SearchOptions {
+            metric: Metric::Fastest,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.search(1, 5, &fastest).unwrap().route,
+            graph.search(1, 5, &dijkstra).unwrap().route
+        );
+
+        graph.add_edge_two_way((1, 51.5074, 0.1278), (5, 51.5, 0.2));
+        assert!(graph.landmarks().is_none());
+    }
+
+    #[test]
+    fn test_landmarks_serde() {
+        let mut graph = random_graph(2, 40, 100);
+        graph.build_landmarks(3, LandmarkStrategy::Avoid, Metric::Shortest);
+
+        let loaded: Graph = serde_json::from_str(&graph.to_json()).unwrap();
+
+        assert_eq!(loaded.landmarks(), graph.landmarks());
+    }
+
+    #[test]
+    fn test_parse_strategy() {
+        for strategy in STRATEGIES {
+            assert_eq!(
+                strategy.to_string().parse::<LandmarkStrategy>(),
+                Ok(strategy)
+            );
+        }
+        assert_eq!(
+            "random".parse::<LandmarkStrategy>(),
+            Err(PathError::UnknownLandmarkStrategy("random".to_string()))
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use ordered_float::OrderedFloat;
+use serde::Serialize;
+
+use super::Graph;
+use crate::metric::Metric;
+
+/// Costs of the cheapest paths from every source to every target
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DistanceMatrix {
+    pub metric: Metric,
+    pub sources: Vec<u64>,
+    pub targets: Vec<u64>,
+    /// One row per source with one entry per target, `None` when the target can't be reached
+    pub costs: Vec<Vec<Option<f32>>>,
+}
+
+impl DistanceMatrix {
+    /// Cost from the source at index `source` to the target at index `target`
+    pub fn get(&self, source: usize, target: usize) -> Option<f32> {
+        self.costs.get(source)?.get(target).copied().flatten()
+    }
+
+    /// A header row of the target ids, then a row per source starting with its id
+    /// Unreachable targets are left empty
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("source");
+        for target in &self.targets {
+            csv.push_str(&format!(",{}", target));
+        }
+        csv.push('\n');
+
+        for (source, row) in self.sources.iter().zip(&self.costs) {
+            csv.push_str(&source.to_string());
+            for cost in row {
+                csv.push(',');
+                if let Some(cost) = cost {
+                    csv.push_str(&cost.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap()
+    }
+}
+
+impl Graph {
+    /// Shortest metric costs from every source to every target, with one search per source
+    pub fn distance_matrix(&self, sources: &[u64], targets: &[u64]) -> DistanceMatrix {
+        self.distance_matrix_with(sources, targets, Metric::Shortest)
+    }
+
+    /// Same as `distance_matrix` but for the metric
+    pub fn distance_matrix_with(
+        &self,
+        sources: &[u64],
+        targets: &[u64],
+        metric: Metric,
+    ) -> DistanceMatrix {
+        let costs = sources
+            .iter()
+            .map(|&source| {
+                let costs = self.costs_to_targets(source, targets, metric);
+                targets
+                    .iter()
+                    .map(|target| costs.get(target).copied())
+                    .collect()
+            })
+            .collect();
+
+        DistanceMatrix {
+            metric,
+            sources: sources.to_vec(),
+            targets: targets.to_vec(),
+            costs,
+        }
+    }
+
+    /// Dijkstra from the source that stops once every target is settled
+    fn costs_to_targets(&self, source: u64, targets: &[u64], metric: Metric) -> HashMap<u64, f32> {
+        let mut remaining: HashSet<u64> = targets
+            .iter()
+            .copied()
+            .filter(|&target| self.contains_node_id(target))
+            .collect();
+        let mut costs: HashMap<u64, f32> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, u64)>> = BinaryHeap::new();
+
+        if self.contains_node_id(source) {
+            costs.insert(source, 0.0);
+            heap.push(Reverse((OrderedFloat(0.0), source)));
+        }
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            let cost = cost.into_inner();
+            if cost > costs[&node] {
+                continue;
+            }
+
+            remaining.remove(&node);
+            if remaining.is_empty() {
+                break;
+            }
+
+            for edge in &self.adj_list[&node].2 {
+                let new_cost = cost + edge.cost(metric);
+                if new_cost < *costs.get(&edge.to).unwrap_or(&f32::MAX) {
+                    costs.insert(edge.to, new_cost);
+                    heap.push(Reverse((OrderedFloat(new_cost), edge.to)));
+                }
+            }
+        }
+
+        // only the settled costs are final
+        costs.retain(|node, _| !remaining.contains(node));
+        costs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        graph::tests::{one_way_graph, path_cost, random_graph, sample_graph},
+        metric::Metric,
+    };
+
+    #[test]
+    fn test_distance_matrix_matches_dijkstra() {
+        for graph in [sample_graph(), one_way_graph(), random_graph(3, 40, 100)] {
+            let sources = [1, 2, 3, 5];
+            let targets = [5, 4, 1, 42];
+
+            let matrix = graph.distance_matrix(&sources, &targets);
+
+            assert_eq!(matrix.costs.len(), sources.len());
+            for (i, &source) in sources.iter().enumerate() {
+                for (j, &target) in targets.iter().enumerate() {
+                    let path = graph.find_shortest_path(source, target);
+                    let expected = (!path.is_empty()).then(|| path_cost(&graph, &path));
+                    match (matrix.get(i, j), expected) {
+                        (Some(cost), Some(expected)) => assert!((cost - expected).abs() < 1e-3),
+                        (cost, expected) => assert_eq!(cost, expected, "{} -> {}", source, target),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_distance_matrix_unreachable() {
+        let graph = one_way_graph();
+
+        let matrix = graph.distance_matrix(&[5, 1], &[1, 5]);
+
+        // nothing leaves 5
+        assert_eq!(matrix.costs[0], vec![None, Some(0.0)]);
+        assert!(matrix.get(1, 1).is_some());
+    }
+
+    #[test]
+    fn test_distance_matrix_fastest() {
+        let graph = sample_graph();
+
+        let matrix = graph.distance_matrix_with(&[1], &[5], Metric::Fastest);
+
+        assert_eq!(matrix.metric, Metric::Fastest);
+        // 4.924km at 30km/h
+        assert!((matrix.get(0, 0).unwrap() - 590.9).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_distance_matrix_output() {
+        let graph = one_way_graph();
+        let matrix = graph.distance_matrix(&[5, 4], &[5, 3]);
+
+        let csv = matrix.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "source,5,3");
+        assert_eq!(lines[1], "5,0,");
+        assert!(lines[2].starts_with("4,"));
+
+        let json: serde_json::Value = serde_json::from_str(&matrix.to_json()).unwrap();
+        assert_eq!(json["metric"], "shortest");
+        assert_eq!(json["costs"][0][1], serde_json::Value::Null);
+        assert_eq!(json["sources"][1], 4);
+    }
+}
@@ -0,0 +1,158 @@
+use serde::Serialize;
+
+use super::{Graph, Route};
+use crate::{error::PathError, search::SearchOptions};
+
+/// One part of a multi stop route, between two consecutive waypoints
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Leg {
+    pub from: u64,
+    pub to: u64,
+    /// Length of the leg in metres
+    pub distance: f32,
+    /// Travel time of the leg in seconds
+    pub duration: f32,
+}
+
+/// A route through several waypoints in order, with every leg stitched into one route
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MultiStopRoute {
+    pub route: Route,
+    pub legs: Vec<Leg>,
+}
+
+impl Graph {
+    /// Routes through the nodes nearest to each `(lat, lon)` waypoint in order
+    pub fn route_through_points(
+        &self,
+        points: &[(f64, f64)],
+        options: &SearchOptions,
+    ) -> Result<MultiStopRoute, PathError> {
+        self.route_through(&self.snap_points(points)?, options)
+    }
+
+    /// Routes from the first waypoint through the others in order, each leg searched with the
+    /// options
+    /// Fails on the first leg whose end can't be reached from its start
+    pub fn route_through(
+        &self,
+        waypoints: &[u64],
+        options: &SearchOptions,
+    ) -> Result<MultiStopRoute, PathError> {
+        if waypoints.len() < 2 {
+            return Err(PathError::TooFewWaypoints(waypoints.len()));
+        }
+
+        let mut route = MultiStopRoute {
+            route: Route::empty(options.metric),
+            legs: Vec::new(),
+        };
+        for (index, pair) in waypoints.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            let leg = self.search(from, to, options)?.route;
+            if leg.is_empty() {
+                return Err(PathError::UnreachableLeg {
+                    leg: index,
+                    from,
+                    to,
+                });
+            }
+
+            route.legs.push(Leg {
+                from,
+                to,
+                distance: leg.distance,
+                duration: leg.duration,
+            });
+            route.route.append(leg);
+        }
+
+        route.route.set_polyline(options.polyline)?;
+        Ok(route)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::PathError,
+        graph::{
+            tests::{one_way_graph, sample_graph},
+            Graph,
+        },
+        search::SearchOptions,
+    };
+
+    #[test]
+    fn test_route_through() {
+        let graph = sample_graph();
+
+        let route = graph
+            .route_through(&[3, 1, 5], &SearchOptions::default())
+            .unwrap();
+
+        let first = graph.find_shortest_path(3, 1);
+        let second = graph.find_shortest_path(1, 5);
+        assert_eq!(
+            route.route.node_ids(),
+            [first.node_ids(), second.node_ids()[1..].to_vec()].concat()
+        );
+        assert_eq!(
+            route.route.edges.len(),
+            first.edges.len() + second.edges.len()
+        );
+        assert_eq!(route.legs.len(), 2);
+        assert_eq!((route.legs[0].from, route.legs[0].to), (3, 1));
+        assert_eq!((route.legs[1].from, route.legs[1].to), (1, 5));
+        // 3 -> 2 -> 1 is 10km + 5km, 1 -> 4 -> 5 is 1.92km + 3.004km
+        assert!((route.legs[0].distance - 15000.0).abs() < 100.0);
+        assert!((route.legs[1].distance - 4920.0).abs() < 100.0);
+        assert!(route.legs.iter().all(|leg| leg.duration > 0.0));
+        assert!(
+            (route.route.distance - route.legs.iter().map(|leg| leg.distance).sum::<f32>()).abs()
+                < 0.1
+        );
+    }
+
+    #[test]
+    fn test_route_through_repeated_waypoint() {
+        let graph = sample_graph();
+
+        let route = graph
+            .route_through(&[1, 1, 4], &SearchOptions::default())
+            .unwrap();
+
+        assert_eq!(route.route.node_ids(), vec![1, 4]);
+        assert_eq!(route.legs[0].distance, 0.0);
+    }
+
+    #[test]
+    fn test_unreachable_leg() {
+        let graph = one_way_graph();
+
+        // nothing leaves 5
+        assert_eq!(
+            graph.route_through(&[1, 5, 2], &SearchOptions::default()),
+            Err(PathError::UnreachableLeg {
+                leg: 1,
+                from: 5,
+                to: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_too_few_waypoints() {
+        let graph = sample_graph();
+
+        assert_eq!(
+            graph.route_through(&[1], &SearchOptions::default()),
+            Err(PathError::TooFewWaypoints(1))
+        );
+        assert_eq!(
+            Graph::new()
+                .route_through_points(&[(51.5, 0.1), (51.5, 0.2)], &SearchOptions::default()),
+            Err(PathError::NoNearbyNode(0))
+        );
+    }
+}
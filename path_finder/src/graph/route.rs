@@ -0,0 +1,318 @@
+use serde::Serialize;
+
+use super::Graph;
+use crate::{error::PathError, metric::Metric, polyline};
+
+/// A path found by a search with its totals and what is known about every edge on it
+/// Empty when the end can't be reached
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Route {
+    /// The metric the edges were chosen by
+    pub metric: Metric,
+    /// Sum of the edge costs and turn penalties for the metric, what the search minimised
+    pub cost: f32,
+    /// Share of the cost that is turn penalties, 0 unless the route was searched with turn costs
+    pub turn_penalty: f32,
+    /// Length in metres
+    pub distance: f32,
+    /// Estimated travel time in seconds along the edges, turn penalties not included
+    pub duration: f32,
+    pub nodes: Vec<RouteNode>,
+    /// One per pair of consecutive nodes
+    pub edges: Vec<RouteEdge>,
+    /// The nodes as an encoded polyline, when it was asked for
+    pub polyline: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RouteNode {
+    pub id: u64,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteEdge {
+    pub from: u64,
+    pub to: u64,
+    /// The OSM way the edge was built from, `None` for edges added by hand
+    pub way_id: Option<u64>,
+    /// The `highway` tag of the way, like `primary` or `residential`
+    pub highway: Option<String>,
+    /// Length in metres
+    pub distance: f32,
+    /// Travel time in seconds
+    pub duration: f32,
+    /// Speed the travel time was estimated at in `km/h`
+    pub speed: f32,
+}
+
+impl Route {
+    pub(super) fn empty(metric: Metric) -> Self {
+        Route {
+            metric,
+            cost: 0.0,
+            turn_penalty: 0.0,
+            distance: 0.0,
+            duration: 0.0,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            polyline: None,
+        }
+    }
+
+    /// Continues the route with one starting where it ends
+    pub(super) fn append(&mut self, other: Route) {
+        // the first node of the other route is the last node of this one
+        let skip = usize::from(!self.nodes.is_empty());
+        self.nodes.extend(other.nodes.into_iter().skip(skip));
+        self.edges.extend(other.edges);
+        self.cost += other.cost;
+        self.turn_penalty += other.turn_penalty;
+        self.distance += other.distance;
+        self.duration += other.duration;
+        self.polyline = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node_ids(&self) -> Vec<u64> {
+        self.nodes.iter().map(|node| node.id).collect()
+    }
+
+    /// The nodes as `(id, lat, lon)`
+    pub fn path(&self) -> Vec<(u64, f64, f64)> {
+        self.nodes
+            .iter()
+            .map(|node| (node.id, node.lat, node.lon))
+            .collect()
+    }
+
+    /// The nodes encoded with the polyline algorithm at `precision` decimal places
+    pub fn encode_polyline(&self, precision: u32) -> String {
+        let points: Vec<(f64, f64)> = self.nodes.iter().map(|node| (node.lat, node.lon)).collect();
+        polyline::encode(&points, precision)
+    }
+
+    /// Sets `polyline` to the nodes encoded at the precision when one is given, which has to be 5
+    /// or 6
+    pub fn set_polyline(&mut self, precision: Option<u32>) -> Result<(), PathError> {
+        self.polyline = match precision {
+            Some(precision @ (polyline::GOOGLE_PRECISION | polyline::OSRM_PRECISION)) => {
+                Some(self.encode_polyline(precision))
+            }
+            Some(precision) => return Err(PathError::UnsupportedPrecision(precision)),
+            None => None,
+        };
+        Ok(())
+    }
+}
+
+impl Graph {
+    /// The route along the path, following the edges a search minimising the metric takes
+    pub(super) fn to_route(&self, path: &[(u64, f64, f64)], metric: Metric) -> Route {
+        let edges: Vec<RouteEdge> = path
+            .windows(2)
+            .filter_map(|pair| {
+                let edge = self.cheapest_edge(pair[0].0, pair[1].0, metric)?;
+                Some(RouteEdge {
+                    from: pair[0].0,
+                    to: pair[1].0,
+                    way_id: edge.way_id,
+                    highway: edge
+                        .way_id
+                        .and_then(|id| self.ways.get(&id))
+                        .and_then(|way| way.highway.clone()),
+                    distance: edge.distance * 1000.0,
+                    duration: edge.duration,
+                    speed: if edge.duration > 0.0 {
+                        edge.distance / edge.duration * 3600.0
+                    } else {
+                        0.0
+                    },
+                })
+            })
+            .collect();
+
+        Route {
+            metric,
+            cost: path
+                .windows(2)
+                .filter_map(|pair| self.cheapest_edge(pair[0].0, pair[1].0, metric))
+                .map(|edge| edge.cost(metric))
+                .sum(),
+            turn_penalty: 0.0,
+            distance: edges.iter().map(|edge| edge.distance).sum(),
+            duration: edges.iter().map(|edge| edge.duration).sum(),
+            nodes: path
+                .iter()
+                .map(|&(id, lat, lon)| RouteNode { id, lat, lon })
+                .collect(),
+            edges,
+            polyline: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::PathError,
+        graph::{
+            tests::{osm_data_at, random_graph, sample_graph, way},
+            Graph,
+        },
+        metric::Metric,
+        polyline,
+        search::SearchOptions,
+    };
+
+    #[test]
+    fn test_route_totals() {
+        let graph = sample_graph();
+
+        let route = graph.find_shortest_path(1, 5);
+
+        assert_eq!(route.node_ids(), vec![1, 4, 5]);
+        assert_eq!(route.edges.len(), 2);
+        // 1.92km + 3.004km
+        assert!((route.distance - 4924.0).abs() < 10.0);
+        assert!((route.cost - route.distance / 1000.0).abs() < 1e-3);
+        assert!(route
+            .edges
+            .iter()
+            .all(|edge| edge.way_id.is_none() && edge.highway.is_none()));
+        // hand added edges are travelled at the fallback speed
+        assert!(route
+            .edges
+            .iter()
+            .all(|edge| (edge.speed - 30.0).abs() < 0.01));
+        assert!((route.duration - 590.9).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_route_edges_from_osm() {
+        let data = osm_data_at(
+            &[
+                (1, 50.8365, -0.79),
+                (2, 50.8365, -0.785),
+                (3, 50.84, -0.785),
+            ],
+            vec![
+                way(
+                    10,
+                    &[1, 2],
+                    &[("highway", "primary"), ("maxspeed", "40 mph")],
+                ),
+                way(11, &[2, 3], &[("highway", "residential")]),
+            ],
+            Vec::new(),
+        );
+        let graph = Graph::from_osm_data(&data).unwrap();
+
+        let options = SearchOptions {
+            metric: Metric::Fastest,
+            ..Default::default()
+        };
+        let route = graph.search(1, 3, &options).unwrap().route;
+
+        assert_eq!(route.metric, Metric::Fastest);
+        assert_eq!(route.edges[0].way_id, Some(10));
+        assert_eq!(route.edges[0].highway.as_deref(), Some("primary"));
+        assert!((route.edges[0].speed - 64.37).abs() < 0.01);
+        assert_eq!(route.edges[1].highway.as_deref(), Some("residential"));
+        assert!((route.cost - route.duration).abs() < 1e-3);
+        let distance: f32 = route.edges.iter().map(|edge| edge.distance).sum();
+        assert_eq!(route.distance, distance);
+    }
+
+    #[test]
+    fn test_empty_route() {
+        let graph = sample_graph();
+
+        let unreachable = graph.find_shortest_path(1, 42);
+        assert!(unreachable.is_empty());
+        assert_eq!(
+            (unreachable.cost, unreachable.distance, unreachable.duration),
+            (0.0, 0.0, 0.0)
+        );
+
+        let single = graph.find_shortest_path(2, 2);
+        assert_eq!(single.node_ids(), vec![2]);
+        assert!(single.edges.is_empty());
+    }
+
+    #[test]
+    fn test_route_serialises_as_objects() {
+        let graph = sample_graph();
+
+        let json = serde_json::to_value(graph.find_shortest_path(1, 4)).unwrap();
+
+        assert_eq!(json["metric"], "shortest");
+        assert_eq!(json["nodes"][0]["id"], 1);
+        assert!(json["nodes"][1]["lat"].is_number());
+        assert_eq!(json["edges"][0]["from"], 1);
+        assert_eq!(json["edges"][0]["to"], 4);
+        assert!(json["edges"][0]["way_id"].is_null());
+        assert!(json["edges"][0]["speed"].is_number());
+        assert!(json["polyline"].is_null());
+    }
+
+    #[test]
+    fn test_route_polyline() {
+        let graph = random_graph(3, 60, 200);
+        let route = graph.find_shortest_path(0, 59);
+        assert!(route.nodes.len() > 2);
+        assert!(route.polyline.is_none());
+
+        for precision in [5, 6] {
+            let options = SearchOptions {
+                polyline: Some(precision),
+                ..Default::default()
+            };
+            let encoded = graph
+                .search(0, 59, &options)
+                .unwrap()
+                .route
+                .polyline
+                .unwrap();
+
+            let decoded = polyline::decode(&encoded, precision).unwrap();
+            assert_eq!(decoded.len(), route.nodes.len());
+            let bound = 0.5 / 10f64.powi(precision as i32) + 1e-12;
+            for (node, (lat, lon)) in route.nodes.iter().zip(decoded) {
+                assert!((node.lat - lat).abs() <= bound && (node.lon - lon).abs() <= bound);
+            }
+        }
+
+        let options = SearchOptions {
+            polyline: Some(7),
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.search(0, 59, &options),
+            Err(PathError::UnsupportedPrecision(7))
+        );
+    }
+
+    #[test]
+    fn test_multi_stop_polyline_covers_every_leg() {
+        let graph = sample_graph();
+
+        let options = SearchOptions {
+            polyline: Some(5),
+            ..Default::default()
+        };
+        let route = graph.route_through(&[3, 1, 5], &options).unwrap().route;
+
+        assert_eq!(route.polyline, Some(route.encode_polyline(5)));
+        assert_eq!(
+            polyline::decode(route.polyline.as_ref().unwrap(), 5)
+                .unwrap()
+                .len(),
+            route.nodes.len()
+        );
+    }
+}
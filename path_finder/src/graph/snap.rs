@@ -0,0 +1,470 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+use serde::Serialize;
+
+use super::{turn_aware::Arrival, Graph, RouteNode};
+use crate::{error::PathError, metric::Metric};
+
+/// A point projected onto the nearest road segment
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EdgeSnap {
+    /// The nodes at the ends of the segment
+    pub from: u64,
+    pub to: u64,
+    pub lat: f64,
+    pub lon: f64,
+    /// Share of the way from `from` to `to` the projection is at
+    pub fraction: f64,
+    /// How far the point was from the projection in metres
+    pub offset: f64,
+}
+
+/// A path between two points snapped onto road segments
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PointPath {
+    pub start: EdgeSnap,
+    pub end: EdgeSnap,
+    /// The graph nodes passed between the two projections, empty when both are on one segment
+    pub nodes: Vec<RouteNode>,
+    /// `(lat, lon)` of the whole path, from the start projection through the nodes to the end
+    /// projection
+    pub geometry: Vec<(f64, f64)>,
+    /// Cost for the metric, including the partial segments at both ends
+    pub cost: f32,
+    /// Length in metres
+    pub distance: f32,
+}
+
+/// Where the route enters or leaves the graph, with the cost, `km` and way of the partial segment
+type Access = (u64, f32, f32, Option<u64>);
+
+impl Graph {
+    /// Projects the point onto the nearest segment of the graph
+    pub fn snap_to_edge(&self, lat: f64, lon: f64) -> Option<EdgeSnap> {
+        let point = [lat, lon];
+        let lon_scale = lat.to_radians().cos();
+        let mut best: Option<(f64, EdgeSnap)> = None;
+
+        // the tree orders by plain degrees, which are never shorter than the projected distance
+        // and at most `1 / lon_scale` times longer, so nothing further away can be nearer
+        for (segment, distance_2) in self
+            .segment_rtree
+            .nearest_neighbor_iter_with_distance_2(&point)
+        {
+            if best.is_some_and(|(nearest, _)| distance_2.sqrt() * lon_scale > nearest) {
+                break;
+            }
+
+            let (fraction, [snap_lat, snap_lon]) = segment.project(&point);
+            let projected =
+                ((snap_lat - lat).powi(2) + ((snap_lon - lon) * lon_scale).powi(2)).sqrt();
+            if best.is_none_or(|(nearest, _)| projected < nearest) {
+                let offset =
+                    Graph::calculate_distance((lat, lon), (snap_lat, snap_lon)) as f64 * 1000.0;
+                let snap = EdgeSnap {
+                    from: segment.from,
+                    to: segment.to,
+                    lat: snap_lat,
+                    lon: snap_lon,
+                    fraction,
+                    offset,
+                };
+                best = Some((projected, snap));
+            }
+        }
+
+        best.map(|(_, snap)| snap)
+    }
+
+    /// Snaps both points onto their nearest segments and finds the cheapest path between the
+    /// projections
+    /// Turns forbidden by the turn restrictions of the graph are avoided unless
+    /// `ignore_turn_restrictions` is set, turn costs are not taken into account
+    /// Returns `None` when the end can't be reached
+    pub fn route_between_points(
+        &self,
+        start: (f64, f64),
+        end: (f64, f64),
+        metric: Metric,
+        ignore_turn_restrictions: bool,
+    ) -> Result<Option<PointPath>, PathError> {
+        let start = self
+            .snap_to_edge(start.0, start.1)
+            .ok_or(PathError::NoNearbyNode(0))?;
+        let end = self
+            .snap_to_edge(end.0, end.1)
+            .ok_or(PathError::NoNearbyNode(1))?;
+        Ok(self.route_between_snaps(&start, &end, metric, ignore_turn_restrictions))
+    }
+
+    /// Cheapest path from one projection to another, starting and ending part way along their
+    /// segments
+    /// Like the edge based searches every node can be settled once per way arriving at it, so turn
+    /// restrictions apply to the partial segments at both ends as well
+    pub fn route_between_snaps(
+        &self,
+        start: &EdgeSnap,
+        end: &EdgeSnap,
+        metric: Metric,
+        ignore_turn_restrictions: bool,
+    ) -> Option<PointPath> {
+        let allowed = |via: u64, from_way: Option<u64>, to_way: Option<u64>| {
+            ignore_turn_restrictions || self.is_turn_allowed(via, from_way, to_way)
+        };
+        // right at a node no edge is needed to get there
+        let partial = |from: u64, to: u64, share: f64| match share {
+            0.0 => Some((to, 0.0, 0.0, None)),
+            _ => self.cheapest_edge(from, to, metric).map(|edge| {
+                (
+                    to,
+                    edge.cost(metric) * share as f32,
+                    edge.distance * share as f32,
+                    edge.way_id,
+                )
+            }),
+        };
+        // leaving the start towards either end of its segment the edges allow
+        let exits: Vec<Access> = [
+            partial(start.from, start.to, 1.0 - start.fraction),
+            partial(start.to, start.from, start.fraction),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        // arriving at the end from either end of its segment, keyed by the node it is arrived from
+        let entries: HashMap<u64, Access> = [
+            partial(end.to, end.from, 1.0 - end.fraction)
+                .map(|(_, cost, km, way)| (end.to, (end.to, cost, km, way))),
+            partial(end.from, end.to, end.fraction)
+                .map(|(_, cost, km, way)| (end.from, (end.from, cost, km, way))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let mut best: Option<(f32, f32, Option<Arrival>)> = self
+            .along_segment(start, end, metric)
+            .map(|(cost, km)| (cost, km, None));
+        let mut costs: HashMap<Arrival, f32> = HashMap::new();
+        let mut predecessors: HashMap<Arrival, Arrival> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, Arrival)>> = BinaryHeap::new();
+        for &(node, cost, _, way) in &exits {
+            if cost < *costs.get(&(node, way)).unwrap_or(&f32::MAX) {
+                costs.insert((node, way), cost);
+                heap.push(Reverse((OrderedFloat(cost), (node, way))));
+            }
+        }
+
+        while let Some(Reverse((cost, state))) = heap.pop() {
+            let cost = cost.into_inner();
+            let (node, incoming_way) = state;
+            if best.is_some_and(|(best_cost, _, _)| cost >= best_cost) {
+                break;
+            }
+            if cost > costs[&state] {
+                continue;
+            }
+            if let Some(&(_, entry_cost, entry_km, entry_way)) = entries.get(&node) {
+                if allowed(node, incoming_way, entry_way)
+                    && best.is_none_or(|(best_cost, _, _)| cost + entry_cost < best_cost)
+                {
+                    best = Some((cost + entry_cost, entry_km, Some(state)));
+                }
+            }
+
+            for edge in &self.adj_list[&node].2 {
+                if !allowed(node, incoming_way, edge.way_id) {
+                    continue;
+                }
+                let next = (edge.to, edge.way_id);
+                let new_cost = cost + edge.cost(metric);
+                if new_cost < *costs.get(&next).unwrap_or(&f32::MAX) {
+                    costs.insert(next, new_cost);
+                    predecessors.insert(next, state);
+                    heap.push(Reverse((OrderedFloat(new_cost), next)));
+                }
+            }
+        }
+
+        let (cost, end_km, last) = best?;
+        let path: Vec<(u64, f64, f64)> = match last {
+            Some(last) => {
+                let mut states = vec![last];
+                while let Some(&prev) = predecessors.get(states.last().unwrap()) {
+                    states.push(prev);
+                }
+                let ids: Vec<u64> = states.into_iter().rev().map(|(node, _)| node).collect();
+                self.with_coordinates(&ids)
+            }
+            None => Vec::new(),
+        };
+
+        let km = match path.first() {
+            Some(&(first, _, _)) => {
+                let start_km = exits
+                    .iter()
+                    .find(|exit| exit.0 == first)
+                    .map_or(0.0, |exit| exit.2);
+                start_km + self.path_totals(&path, metric).0 + end_km
+            }
+            None => end_km,
+        };
+        let geometry = std::iter::once((start.lat, start.lon))
+            .chain(path.iter().map(|&(_, lat, lon)| (lat, lon)))
+            .chain(std::iter::once((end.lat, end.lon)))
+            .collect();
+        let nodes = path
+            .into_iter()
+            .map(|(id, lat, lon)| RouteNode { id, lat, lon })
+            .collect();
+        let distance = km * 1000.0;
+
+        Some(PointPath {
+            start: *start,
+            end: *end,
+            nodes,
+            geometry,
+            cost,
+            distance,
+        })
+    }
+
+    /// Cost and `km` of going straight from the start to the end when both are on the same segment
+    /// and the edge between them runs that way
+    fn along_segment(
+        &self,
+        start: &EdgeSnap,
+        end: &EdgeSnap,
+        metric: Metric,
+    ) -> Option<(f32, f32)> {
+        // the same segment may have been snapped to from either of its ends
+        let end_fraction = if (end.from, end.to) == (start.from, start.to) {
+            end.fraction
+        } else if (end.from, end.to) == (start.to, start.from) {
+            1.0 - end.fraction
+        } else {
+            return None;
+        };
+
+        let (from, to, share) = if end_fraction >= start.fraction {
+            (start.from, start.to, end_fraction - start.fraction)
+        } else {
+            (start.to, start.from, start.fraction - end_fraction)
+        };
+        self.cheapest_edge(from, to, metric).map(|edge| {
+            (
+                edge.cost(metric) * share as f32,
+                edge.distance * share as f32,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PointPath;
+    use crate::{
+        error::PathError,
+        graph::{
+            tests::{osm_data_at, path_cost, random_graph, restriction, way},
+            Graph,
+        },
+        metric::Metric,
+        profile::Profile,
+    };
+
+    /// A long straight road 1 - 2 - 3 along a parallel, with 4 off to the side of 2
+    fn long_road() -> Graph {
+        let mut graph = Graph::new();
+        graph.add_edge_two_way((1, 50.0, 0.0), (2, 50.0, 0.1));
+        graph.add_edge_two_way((2, 50.0, 0.1), (3, 50.0, 0.2));
+        graph.add_edge_two_way((2, 50.0, 0.1), (4, 50.05, 0.1));
+        graph
+    }
+
+    #[test]
+    fn test_snap_to_edge() {
+        let graph = long_road();
+
+        // just north of the middle of 1 - 2, far from any node
+        let snap = graph.snap_to_edge(50.001, 0.05).unwrap();
+
+        assert_eq!((snap.from.min(snap.to), snap.from.max(snap.to)), (1, 2));
+        assert!((snap.lat - 50.0).abs() < 1e-9);
+        assert!((snap.lon - 0.05).abs() < 1e-9);
+        assert!((snap.offset - 111.2).abs() < 0.5);
+        assert_eq!(
+            graph
+                .nearest_neighbor(50.001, 0.05)
+                .map(|node| node == 1 || node == 2),
+            Some(true)
+        );
+        assert!(Graph::new().snap_to_edge(50.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_snap_to_edge_matches_brute_force() {
+        let graph = random_graph(5, 60, 150);
+
+        for (lat, lon) in [
+            (50.83, -0.78),
+            (50.825, -0.79),
+            (50.845, -0.76),
+            (50.81, -0.81),
+        ] {
+            let snap = graph.snap_to_edge(lat, lon).unwrap();
+
+            let scale = f64::cos(lat.to_radians());
+            let nearest = graph
+                .segment_rtree
+                .iter()
+                .map(|segment| {
+                    let (_, [snap_lat, snap_lon]) = segment.project(&[lat, lon]);
+                    ((snap_lat - lat).powi(2) + ((snap_lon - lon) * scale).powi(2)).sqrt()
+                })
+                .fold(f64::INFINITY, f64::min);
+            let found = ((snap.lat - lat).powi(2) + ((snap.lon - lon) * scale).powi(2)).sqrt();
+            assert!((found - nearest).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_route_between_points_uses_partial_edges() {
+        let graph = long_road();
+
+        // a quarter of the way along 1 - 2 to half way along 2 - 3
+        let path = graph
+            .route_between_points((50.001, 0.025), (49.999, 0.15), Metric::Shortest, false)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            path.nodes.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert_eq!(path.geometry.len(), 3);
+        let (first, last) = (path.geometry[0], path.geometry[2]);
+        assert!((first.0 - 50.0).abs() < 1e-9 && (first.1 - 0.025).abs() < 1e-9);
+        assert!((last.0 - 50.0).abs() < 1e-9 && (last.1 - 0.15).abs() < 1e-9);
+        let expected = Graph::calculate_distance((50.0, 0.025), (50.0, 0.15));
+        assert!((path.distance - expected * 1000.0).abs() < 1.0);
+        assert!((path.cost - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_route_between_points_on_one_segment() {
+        let graph = long_road();
+
+        let path = graph
+            .route_between_points((50.0, 0.16), (50.0, 0.12), Metric::Shortest, false)
+            .unwrap()
+            .unwrap();
+
+        assert!(path.nodes.is_empty());
+        assert_eq!(path.geometry.len(), 2);
+        assert!(
+            (path.distance - Graph::calculate_distance((50.0, 0.12), (50.0, 0.16)) * 1000.0).abs()
+                < 1.0
+        );
+    }
+
+    #[test]
+    fn test_route_between_points_one_way() {
+        let mut graph = Graph::new();
+        graph.add_edge_one_way((1, 50.0, 0.0), (2, 50.0, 0.1));
+        graph.add_edge_one_way((2, 50.0, 0.1), (3, 50.0, 0.2));
+
+        // against the traffic, even along a single segment
+        assert_eq!(
+            graph
+                .route_between_points((50.0, 0.15), (50.0, 0.05), Metric::Shortest, false)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            graph
+                .route_between_points((50.0, 0.08), (50.0, 0.02), Metric::Shortest, false)
+                .unwrap(),
+            None
+        );
+        let path = graph
+            .route_between_points((50.0, 0.05), (50.0, 0.15), Metric::Shortest, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            path.nodes.iter().map(|node| node.id).collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_route_between_nodes_matches_dijkstra() {
+        let graph = random_graph(2, 40, 120);
+
+        for (start, end) in [(0, 39), (3, 17), (25, 8), (11, 30)] {
+            let expected = graph.find_shortest_path(start, end);
+            let path = graph.route_between_points(
+                graph.coordinates(start).unwrap(),
+                graph.coordinates(end).unwrap(),
+                Metric::Shortest,
+                false,
+            );
+            match path.unwrap() {
+                Some(path) => assert!(
+                    (path.cost - path_cost(&graph, &expected)).abs() < 1e-3,
+                    "{} -> {}",
+                    start,
+                    end
+                ),
+                None => assert!(expected.is_empty()),
+            }
+        }
+
+        assert_eq!(
+            Graph::new().route_between_points((50.0, 0.0), (50.0, 0.1), Metric::Shortest, false),
+            Err(PathError::NoNearbyNode(0))
+        );
+    }
+
+    #[test]
+    fn test_route_between_points_follows_restrictions() {
+        // junction 5 where the left turn from the arm to 1 into the arm to 3 is forbidden, so the
+        // arm to 3 is only reached by turning back at 2 or round the loop through 6
+        let data = osm_data_at(
+            &[
+                (5, 50.8365, -0.78),
+                (1, 50.8365, -0.7814),
+                (2, 50.8365, -0.7772),
+                (3, 50.8374, -0.78),
+                (6, 50.8383, -0.7772),
+            ],
+            vec![
+                way(100, &[1, 5], &[("highway", "residential")]),
+                way(101, &[5, 2], &[("highway", "residential")]),
+                way(102, &[3, 5], &[("highway", "residential")]),
+                way(104, &[2, 6, 3], &[("highway", "residential")]),
+            ],
+            vec![restriction(1, "no_left_turn", 100, 5, 102)],
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        let ids = |path: &PointPath| path.nodes.iter().map(|node| node.id).collect::<Vec<_>>();
+        let (start, end) = ((50.8365, -0.7807), (50.8369, -0.78));
+
+        let ignoring = graph
+            .route_between_points(start, end, Metric::Shortest, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids(&ignoring), vec![5]);
+        let path = graph
+            .route_between_points(start, end, Metric::Shortest, false)
+            .unwrap()
+            .unwrap();
+        // turning back is shorter than the loop
+        assert_eq!(ids(&path), vec![5, 2, 5]);
+        assert!(path.cost > ignoring.cost);
+    }
+}
@@ -0,0 +1,412 @@
+use serde::Serialize;
+
+use super::{Graph, MultiStopRoute};
+use crate::{error::PathError, search::SearchOptions};
+
+/// Smallest saving an improvement has to make, so rounding can't make the search go back and forth
+const MIN_IMPROVEMENT: f32 = 1e-4;
+
+/// Visiting order of a delivery style tour and the route that follows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tour {
+    /// Indices into the stops in the order they are visited
+    pub order: Vec<usize>,
+    /// Every node of the tour in visiting order, from the start to the last stop or the end
+    pub waypoints: Vec<u64>,
+    /// Total cost of the routed legs, turn penalties included when the tour was planned with turn
+    /// costs
+    pub cost: f32,
+    pub route: MultiStopRoute,
+}
+
+impl Graph {
+    /// Visits every stop once starting from `start`, finishing at `end` when given,
+    /// in a short order found by nearest neighbour then improved with 2-opt and Or-opt
+    /// The order is a heuristic and not guaranteed to be the best one
+    /// The costs it is planned with are searched like the legs, so they follow the same
+    /// restrictions and turn costs
+    pub fn plan_tour(
+        &self,
+        start: u64,
+        stops: &[u64],
+        end: Option<u64>,
+        options: &SearchOptions,
+    ) -> Result<Tour, PathError> {
+        let nodes: Vec<u64> = std::iter::once(start)
+            .chain(stops.iter().copied())
+            .chain(end)
+            .collect();
+        let matrix = self.tour_matrix(&nodes, options)?;
+
+        // positions in `nodes`, the start at 0, the stops from 1 and the end last
+        let mut sequence = nearest_neighbour(&matrix, stops.len());
+        if end.is_some() {
+            sequence.push(nodes.len() - 1);
+        }
+        let fixed_end = end.is_some();
+        while two_opt(&matrix, &mut sequence, fixed_end)
+            || or_opt(&matrix, &mut sequence, fixed_end)
+        {}
+
+        let waypoints: Vec<u64> = sequence.iter().map(|&position| nodes[position]).collect();
+        let route = if waypoints.len() > 1 {
+            self.route_through(&waypoints, options)?
+        } else {
+            let mut route = self.to_route(&self.with_coordinates(&waypoints), options.metric);
+            route.set_polyline(options.polyline)?;
+            MultiStopRoute {
+                route,
+                legs: Vec::new(),
+            }
+        };
+
+        let order = sequence
+            .iter()
+            .filter(|&&position| (1..=stops.len()).contains(&position))
+            .map(|position| position - 1)
+            .collect();
+
+        Ok(Tour {
+            order,
+            cost: route.route.cost,
+            waypoints,
+            route,
+        })
+    }
+
+    /// Costs between every two of the nodes, `INFINITY` when unreachable
+    /// An edge based search has no one to many version, so it is one search per pair then
+    fn tour_matrix(
+        &self,
+        nodes: &[u64],
+        options: &SearchOptions,
+    ) -> Result<Vec<Vec<f32>>, PathError> {
+        if !self.needs_turn_aware_search(options) {
+            let matrix = self.distance_matrix_with(nodes, nodes, options.metric);
+            return Ok(matrix
+                .costs
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|cost| cost.unwrap_or(f32::INFINITY))
+                        .collect()
+                })
+                .collect());
+        }
+
+        let options = SearchOptions {
+            trace: false,
+            polyline: None,
+            ..options.clone()
+        };
+        nodes
+            .iter()
+            .map(|&from| {
+                nodes
+                    .iter()
+                    .map(|&to| {
+                        let route = self.search(from, to, &options)?.route;
+                        Ok(if route.is_empty() {
+                            f32::INFINITY
+                        } else {
+                            route.cost
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// From the start always goes to the cheapest stop not visited yet
+fn nearest_neighbour(matrix: &[Vec<f32>], stops: usize) -> Vec<usize> {
+    let mut sequence = vec![0];
+    let mut remaining: Vec<usize> = (1..=stops).collect();
+
+    while !remaining.is_empty() {
+        let last = *sequence.last().unwrap();
+        let (index, _) = remaining
+            .iter()
+            .enumerate()
+            .min_by(|(_, &a), (_, &b)| matrix[last][a].total_cmp(&matrix[last][b]))
+            .unwrap();
+        sequence.push(remaining.swap_remove(index));
+    }
+
+    sequence
+}
+
+fn sequence_cost(matrix: &[Vec<f32>], sequence: &[usize]) -> f32 {
+    sequence
+        .windows(2)
+        .map(|pair| matrix[pair[0]][pair[1]])
+        .sum()
+}
+
+/// Positions of the sequence that may move, the start and a fixed end stay where they are
+fn movable(sequence: &[usize], fixed_end: bool) -> std::ops::Range<usize> {
+    1..sequence.len() - usize::from(fixed_end)
+}
+
+/// Applies the first reversal of a stretch of stops that makes the tour cheaper
+/// Costs can differ by direction on one way streets, so the whole tour is costed again
+fn two_opt(matrix: &[Vec<f32>], sequence: &mut [usize], fixed_end: bool) -> bool {
+    let current = sequence_cost(matrix, sequence);
+    let range = movable(sequence, fixed_end);
+
+    for i in range.clone() {
+        for j in i + 1..range.end {
+            sequence[i..=j].reverse();
+            if sequence_cost(matrix, sequence) < current - MIN_IMPROVEMENT {
+                return true;
+            }
+            sequence[i..=j].reverse();
+        }
+    }
+    false
+}
+
+/// Applies the first move of a run of up to 3 stops to another place in the tour that makes it
+/// cheaper
+fn or_opt(matrix: &[Vec<f32>], sequence: &mut Vec<usize>, fixed_end: bool) -> bool {
+    let current = sequence_cost(matrix, sequence);
+    let range = movable(sequence, fixed_end);
+
+    for length in 1..=3 {
+        for from in range.start..range.end.saturating_sub(length - 1) {
+            for to in range.clone() {
+                if (from..from + length).contains(&to) {
+                    continue;
+                }
+
+                // the run goes before an earlier stop `to` or after a later one
+                let mut candidate = sequence.clone();
+                let run: Vec<usize> = candidate.drain(from..from + length).collect();
+                let insert_at = if to > from { to + 1 - length } else { to };
+                candidate.splice(insert_at..insert_at, run);
+
+                if sequence_cost(matrix, &candidate) < current - MIN_IMPROVEMENT {
+                    *sequence = candidate;
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{nearest_neighbour, or_opt, sequence_cost, two_opt};
+    use crate::{
+        error::PathError,
+        graph::{
+            tests::{one_way_graph, osm_data_at, random_graph, restriction, sample_graph, way},
+            Graph,
+        },
+        profile::Profile,
+        search::SearchOptions,
+    };
+
+    /// Every order of the stops with the start first, by brute force
+    fn best_cost(matrix: &[Vec<f32>], stops: usize, fixed_end: bool) -> f32 {
+        fn permute(
+            matrix: &[Vec<f32>],
+            sequence: &mut Vec<usize>,
+            rest: &mut Vec<usize>,
+            end: Option<usize>,
+        ) -> f32 {
+            if rest.is_empty() {
+                let full: Vec<usize> = sequence.iter().copied().chain(end).collect();
+                return sequence_cost(matrix, &full);
+            }
+            let mut best = f32::INFINITY;
+            for index in 0..rest.len() {
+                let next = rest.remove(index);
+                sequence.push(next);
+                best = best.min(permute(matrix, sequence, rest, end));
+                sequence.pop();
+                rest.insert(index, next);
+            }
+            best
+        }
+
+        permute(
+            matrix,
+            &mut vec![0],
+            &mut (1..=stops).collect(),
+            fixed_end.then_some(stops + 1),
+        )
+    }
+
+    #[test]
+    fn test_plan_tour() {
+        let graph = sample_graph();
+
+        let tour = graph
+            .plan_tour(1, &[3, 5, 4], None, &SearchOptions::default())
+            .unwrap();
+
+        // 4 and 5 are on the way to 3
+        assert_eq!(tour.waypoints, vec![1, 4, 5, 3]);
+        assert_eq!(tour.order, vec![2, 1, 0]);
+        assert_eq!(tour.route.legs.len(), 3);
+        assert_eq!(tour.route.route.nodes.first().map(|node| node.id), Some(1));
+        assert_eq!(tour.route.route.nodes.last().map(|node| node.id), Some(3));
+        let leg_distance: f32 = tour.route.legs.iter().map(|leg| leg.distance).sum();
+        assert!((leg_distance / 1000.0 - tour.cost).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_plan_tour_follows_restrictions() {
+        // junction 5 with a short arm west to 1, a long arm east to 2 and north to 3, where west to
+        // north is a forbidden left turn so 3 is only reached from 1 by turning back at 2 or round
+        // the loop through 6
+        let data = osm_data_at(
+            &[
+                (5, 50.8365, -0.78),
+                (1, 50.8365, -0.7814),
+                (2, 50.8365, -0.7772),
+                (3, 50.8374, -0.78),
+                (6, 50.8383, -0.7772),
+            ],
+            vec![
+                way(100, &[1, 5], &[("highway", "residential")]),
+                way(101, &[5, 2], &[("highway", "residential")]),
+                way(102, &[3, 5], &[("highway", "residential")]),
+                way(104, &[2, 6, 3], &[("highway", "residential")]),
+            ],
+            vec![restriction(1, "no_left_turn", 100, 5, 102)],
+        );
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        // ignoring the restriction 3 is nearer, but the turn into it makes 2 the better first stop
+        let ignoring = SearchOptions {
+            ignore_turn_restrictions: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.plan_tour(1, &[3, 2], None, &ignoring).unwrap().order,
+            vec![0, 1]
+        );
+        let tour = graph
+            .plan_tour(1, &[3, 2], None, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(tour.order, vec![1, 0]);
+
+        let legs = graph
+            .route_through(&tour.waypoints, &SearchOptions::default())
+            .unwrap();
+        assert!((tour.cost - legs.route.cost).abs() < 1e-4);
+
+        let turn_costs = SearchOptions {
+            turn_costs: true,
+            ..Default::default()
+        };
+        let tour = graph.plan_tour(1, &[3, 2], None, &turn_costs).unwrap();
+        assert!(tour.route.route.turn_penalty > 0.0);
+        assert!(
+            (tour.cost
+                - graph
+                    .route_through(&tour.waypoints, &turn_costs)
+                    .unwrap()
+                    .route
+                    .cost)
+                .abs()
+                < 1e-4
+        );
+    }
+
+    #[test]
+    fn test_plan_tour_fixed_end() {
+        let graph = sample_graph();
+
+        let tour = graph
+            .plan_tour(1, &[3, 5], Some(4), &SearchOptions::default())
+            .unwrap();
+
+        assert_eq!(tour.waypoints.first(), Some(&1));
+        assert_eq!(tour.waypoints.last(), Some(&4));
+        assert_eq!(tour.waypoints.len(), 4);
+        assert_eq!(tour.route.route.nodes.last().map(|node| node.id), Some(4));
+    }
+
+    #[test]
+    fn test_plan_tour_without_stops() {
+        let graph = sample_graph();
+
+        let tour = graph
+            .plan_tour(2, &[], None, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(tour.waypoints, vec![2]);
+        assert_eq!(tour.route.route.nodes.len(), 1);
+
+        let tour = graph
+            .plan_tour(2, &[], Some(4), &SearchOptions::default())
+            .unwrap();
+        assert_eq!(tour.waypoints, vec![2, 4]);
+    }
+
+    #[test]
+    fn test_plan_tour_unreachable_stop() {
+        let graph = one_way_graph();
+
+        // nothing leaves 5, so it has to be the last stop
+        let tour = graph
+            .plan_tour(1, &[5, 2], None, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(tour.waypoints, vec![1, 2, 5]);
+
+        let result = graph.plan_tour(1, &[5, 2], Some(4), &SearchOptions::default());
+        assert!(matches!(
+            result,
+            Err(PathError::UnreachableLeg { from: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_improvements_get_close_to_optimal() {
+        for seed in 0..4 {
+            let graph: Graph = random_graph(seed, 80, 300);
+            let nodes: Vec<u64> = (0..8).map(|i| i * 9).collect();
+            let matrix: Vec<Vec<f32>> = graph
+                .distance_matrix(&nodes, &nodes)
+                .costs
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|cost| cost.unwrap_or(f32::INFINITY))
+                        .collect()
+                })
+                .collect();
+
+            for fixed_end in [false, true] {
+                let stops = nodes.len() - 1 - usize::from(fixed_end);
+                let mut sequence = nearest_neighbour(&matrix, stops);
+                if fixed_end {
+                    sequence.push(nodes.len() - 1);
+                }
+                let greedy = sequence_cost(&matrix, &sequence);
+                while two_opt(&matrix, &mut sequence, fixed_end)
+                    || or_opt(&matrix, &mut sequence, fixed_end)
+                {}
+
+                let improved = sequence_cost(&matrix, &sequence);
+                let best = best_cost(&matrix, stops, fixed_end);
+                assert!(improved <= greedy);
+                if best.is_finite() {
+                    assert!(
+                        improved <= best * 1.1,
+                        "seed {}: {} against the best {}",
+                        seed,
+                        improved,
+                        best
+                    );
+                }
+                let mut visited = sequence.clone();
+                visited.sort_unstable();
+                assert_eq!(visited, (0..nodes.len()).collect::<Vec<_>>());
+            }
+        }
+    }
+}
@@ -0,0 +1,554 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use ordered_float::OrderedFloat;
+
+use super::{Graph, Route};
+use crate::{metric::Metric, search::SearchOptions, trace::Tracer};
+
+/// A node together with the edge used to reach it, as `(from, index into the edges of from)`
+/// Turn restrictions depend on the way a node was entered from, so the search is over these states
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct State {
+    node: u64,
+    edge: Option<(u64, usize)>,
+}
+
+/// A node with the way it was reached by, `None` before any edge was taken
+/// Lighter than `State` for searches that only need the way for turn restrictions
+pub(super) type Arrival = (u64, Option<u64>);
+
+impl Graph {
+    /// Finds the shortest path that never makes a turn forbidden by a turn restriction
+    pub fn find_shortest_path_turn_aware(&self, start: u64, end: u64) -> Route {
+        let path = self.turn_aware_search(
+            start,
+            end,
+            &SearchOptions::default(),
+            |_| 0.0,
+            Tracer::new(self, None),
+        );
+        self.to_route(&path, Metric::Shortest)
+    }
+
+    /// Finds the shortest path that also pays the turn penalties of the graph
+    pub fn find_shortest_path_with_turn_costs(&self, start: u64, end: u64) -> Route {
+        let options = SearchOptions {
+            turn_costs: true,
+            ..Default::default()
+        };
+        let path = self.turn_aware_search(start, end, &options, |_| 0.0, Tracer::new(self, None));
+        let mut route = self.to_route(&path, Metric::Shortest);
+        self.add_turn_penalties(&mut route);
+        route
+    }
+
+    /// Adds the turn penalties at every node the route passes through to its cost
+    pub(super) fn add_turn_penalties(&self, route: &mut Route) {
+        let penalty: f32 = route
+            .nodes
+            .windows(3)
+            .filter_map(|nodes| {
+                let (from, via, to) = (&nodes[0], &nodes[1], &nodes[2]);
+                let (from, to) = ((from.id, from.lat, from.lon), (to.id, to.lat, to.lon));
+                self.turn_costs
+                    .turn_cost(from, (via.lat, via.lon), to, route.metric)
+            })
+            .sum();
+        route.turn_penalty += penalty;
+        route.cost += penalty;
+    }
+
+    /// Edge based search, every node can be settled once per edge arriving at it
+    /// Dijkstra with a heuristic of 0, `A*` otherwise, where any lower bound of the node based
+    /// search still holds since restrictions only take paths away and turn penalties only ever add
+    /// to them
+    pub(super) fn turn_aware_search<H>(
+        &self,
+        start: u64,
+        end: u64,
+        options: &SearchOptions,
+        heuristic: H,
+        mut tracer: Tracer,
+    ) -> Vec<(u64, f64, f64)>
+    where
+        H: Fn(u64) -> f32,
+    {
+        let metric = options.metric;
+        let turn_costs = options.turn_costs.then_some(&self.turn_costs);
+
+        if !self.contains_node_id(end) {
+            return Vec::new();
+        }
+
+        let mut costs: HashMap<State, f32> = HashMap::new();
+        let mut predecessors: HashMap<State, State> = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(OrderedFloat<f32>, State)>> = BinaryHeap::new();
+        let mut reached = None;
+
+        let start_state = State {
+            node: start,
+            edge: None,
+        };
+        costs.insert(start_state, 0.0);
+        heap.push(Reverse((OrderedFloat(heuristic(start)), start_state)));
+
+        while let Some(Reverse((estimate, state))) = heap.pop() {
+            let cost = OrderedFloat(costs[&state]);
+            if estimate > cost + heuristic(state.node) {
+                continue;
+            }
+
+            tracer.settled(state.node, cost.into_inner());
+            if state.node == end {
+                reached = Some(state);
+                break;
+            }
+
+            let incoming_way = state
+                .edge
+                .and_then(|(from, index)| self.adj_list[&from].2[index].way_id);
+            let Some(&(lat, lon, ref edges)) = self.adj_list.get(&state.node) else {
+                continue;
+            };
+
+            for (index, edge) in edges.iter().enumerate() {
+                if !options.ignore_turn_restrictions
+                    && !self.is_turn_allowed(state.node, incoming_way, edge.way_id)
+                {
+                    continue;
+                }
+
+                let mut turn_cost = 0.0;
+                if let (Some(model), Some((from, _))) = (turn_costs, state.edge) {
+                    let (from_lat, from_lon) = self.coordinates(from).unwrap_or((lat, lon));
+                    let (to_lat, to_lon) = self.coordinates(edge.to).unwrap_or((lat, lon));
+                    match model.turn_cost(
+                        (from, from_lat, from_lon),
+                        (lat, lon),
+                        (edge.to, to_lat, to_lon),
+                        metric,
+                    ) {
+                        Some(cost) => turn_cost = cost,
+                        None => continue,
+                    }
+                }
+
+                let next = State {
+                    node: edge.to,
+                    edge: Some((state.node, index)),
+                };
+                let new_cost = cost + edge.cost(metric) + turn_cost;
+                if new_cost < OrderedFloat(*costs.get(&next).unwrap_or(&f32::MAX)) {
+                    costs.insert(next, new_cost.into_inner());
+                    predecessors.insert(next, state);
+                    tracer.relaxed(state.node, edge.to, new_cost.into_inner());
+                    heap.push(Reverse((new_cost + heuristic(edge.to), next)));
+                }
+            }
+        }
+
+        let Some(mut current) = reached else {
+            return Vec::new();
+        };
+
+        let mut path = Vec::new();
+        loop {
+            if let Some((lat, lon)) = self.coordinates(current.node) {
+                path.push((current.node, lat, lon));
+            }
+            match predecessors.get(&current) {
+                Some(&prev) => current = prev,
+                None => break,
+            }
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// Whether a turn restriction at `via` forbids going from `from_way` into `to_way`
+    /// Edges without a way, like at the start of a search, can always be taken
+    pub(super) fn is_turn_allowed(
+        &self,
+        via: u64,
+        from_way: Option<u64>,
+        to_way: Option<u64>,
+    ) -> bool {
+        let (Some(from_way), Some(to_way)) = (from_way, to_way) else {
+            return true;
+        };
+
+        self.restrictions.get(&via).is_none_or(|restrictions| {
+            restrictions
+                .iter()
+                .all(|restriction| !restriction.forbids(from_way, to_way))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithm::Algorithm,
+        error::PathError,
+        graph::{
+            tests::{one_way_graph, osm_data_at, restriction, sample_graph, way},
+            Edge, Graph, LandmarkStrategy,
+        },
+        metric::Metric,
+        osm_data::{OSMData, Relation},
+        profile::Profile,
+        search::SearchOptions,
+        turn_cost::{TurnCostModel, UTurn},
+    };
+
+    /// Crossroads at node 5 with arms west (1), east (2), north (3) and south (4)
+    /// Each arm is its own way and east and north are also joined round the corner through node 6
+    /// The east arm is one way out of the crossroads so there's no turning back at 2
+    fn crossroads(relations: Vec<Relation>) -> OSMData {
+        osm_data_at(
+            &[
+                (5, 50.8365, -0.7800),
+                (1, 50.8365, -0.7814),
+                (2, 50.8365, -0.7786),
+                (3, 50.8374, -0.7800),
+                (4, 50.8356, -0.7800),
+                (6, 50.8374, -0.7786),
+            ],
+            vec![
+                way(100, &[1, 5], &[("highway", "residential")]),
+                way(
+                    101,
+                    &[5, 2],
+                    &[("highway", "residential"), ("oneway", "yes")],
+                ),
+                way(102, &[3, 5], &[("highway", "residential")]),
+                way(103, &[5, 4], &[("highway", "residential")]),
+                way(104, &[2, 6, 3], &[("highway", "residential")]),
+            ],
+            relations,
+        )
+    }
+
+    /// Junction at 5 with arms west (1), east (2) and north (3), where west to north is a forbidden
+    /// left turn
+    /// The only other way north is a long loop from 2 through 6, or turning back at 2
+    fn loop_junction() -> Graph {
+        let data = osm_data_at(
+            &[
+                (5, 50.8365, -0.7800),
+                (1, 50.8365, -0.7814),
+                (2, 50.8365, -0.7786),
+                (3, 50.8374, -0.7800),
+                (6, 50.8383, -0.7772),
+            ],
+            vec![
+                way(100, &[1, 5], &[("highway", "residential")]),
+                way(101, &[5, 2], &[("highway", "residential")]),
+                way(102, &[3, 5], &[("highway", "residential")]),
+                way(104, &[2, 6, 3], &[("highway", "residential")]),
+            ],
+            vec![restriction(1, "no_left_turn", 100, 5, 102)],
+        );
+        Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap()
+    }
+
+    #[test]
+    fn test_no_left_turn() {
+        let data = crossroads(vec![restriction(1, "no_left_turn", 100, 5, 102)]);
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        // west to north would be a left turn at 5, so go round the corner
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(1, 3).node_ids(),
+            vec![1, 5, 2, 6, 3]
+        );
+        assert_eq!(graph.find_shortest_path(1, 3).node_ids(), vec![1, 5, 3]);
+        // the other turns at 5 are untouched
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(1, 4).node_ids(),
+            vec![1, 5, 4]
+        );
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(3, 1).node_ids(),
+            vec![3, 5, 1]
+        );
+    }
+
+    #[test]
+    fn test_only_straight_on() {
+        let data = crossroads(vec![restriction(1, "only_straight_on", 100, 5, 101)]);
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(1, 2).node_ids(),
+            vec![1, 5, 2]
+        );
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(1, 3).node_ids(),
+            vec![1, 5, 2, 6, 3]
+        );
+        // south is reached by coming back into the crossroads from the north
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(1, 4).node_ids(),
+            vec![1, 5, 2, 6, 3, 5, 4]
+        );
+    }
+
+    const TURN_AWARE: [Algorithm; 3] = [Algorithm::Dijkstra, Algorithm::AStar, Algorithm::Alt];
+    const NODE_BASED: [Algorithm; 3] = [
+        Algorithm::BellmanFord,
+        Algorithm::Bidirectional,
+        Algorithm::ContractionHierarchy,
+    ];
+
+    #[test]
+    fn test_search_honours_restrictions() {
+        let data = crossroads(vec![restriction(1, "no_left_turn", 100, 5, 102)]);
+        let mut graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+        graph.build_landmarks(2, LandmarkStrategy::Farthest, Metric::Shortest);
+        graph.build_contraction_hierarchy(Metric::Shortest);
+
+        for algorithm in TURN_AWARE {
+            let options = SearchOptions {
+                algorithm,
+                trace: true,
+                ..Default::default()
+            };
+            let result = graph.search(1, 3, &options).unwrap();
+            assert_eq!(
+                result.route.node_ids(),
+                vec![1, 5, 2, 6, 3],
+                "{}",
+                algorithm
+            );
+            assert!(!result.trace.unwrap().settled.is_empty());
+        }
+        // the others can't tell which way a node was entered from
+        for algorithm in NODE_BASED {
+            let options = SearchOptions {
+                algorithm,
+                ..Default::default()
+            };
+            assert_eq!(
+                graph.search(1, 3, &options),
+                Err(PathError::UnsupportedWithRestrictions(algorithm))
+            );
+
+            let options = SearchOptions {
+                ignore_turn_restrictions: true,
+                ..options
+            };
+            assert_eq!(
+                graph.search(1, 3, &options).unwrap().route.node_ids(),
+                vec![1, 5, 3],
+                "{}",
+                algorithm
+            );
+        }
+
+        let options = SearchOptions {
+            ignore_turn_restrictions: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.search(1, 3, &options).unwrap().route.node_ids(),
+            vec![1, 5, 3]
+        );
+    }
+
+    #[test]
+    fn test_turn_aware_alt_needs_landmarks() {
+        let data = crossroads(vec![restriction(1, "no_left_turn", 100, 5, 102)]);
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::car()).unwrap();
+
+        let options = SearchOptions {
+            algorithm: Algorithm::Alt,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.search(1, 3, &options),
+            Err(PathError::MissingLandmarks(Metric::Shortest))
+        );
+    }
+
+    #[test]
+    fn test_restrictions_follow_profile() {
+        let data = crossroads(vec![restriction(1, "no_left_turn", 100, 5, 102)]);
+        let graph = Graph::from_osm_data_with_profile(&data, &Profile::foot()).unwrap();
+
+        assert_eq!(
+            graph
+                .search(1, 3, &SearchOptions::default())
+                .unwrap()
+                .route
+                .node_ids(),
+            vec![1, 5, 3]
+        );
+    }
+
+    #[test]
+    fn test_turn_aware_matches_dijkstra_without_restrictions() {
+        for graph in [sample_graph(), one_way_graph()] {
+            for start in 1..=5 {
+                for end in 1..=5 {
+                    assert_eq!(
+                        graph.find_shortest_path_turn_aware(start, end),
+                        graph.find_shortest_path(start, end),
+                        "paths differ for {} -> {}",
+                        start,
+                        end
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_turn_costs_avoid_zig_zag() {
+        let mut graph = Graph::new();
+        let nodes = [
+            (1, 50.0, 0.0),
+            (2, 50.0, 0.001),
+            (3, 50.0, 0.002),
+            (6, 50.001, 0.0),
+            (7, 50.001, 0.002),
+        ];
+        // straight on from 1 to 3 costs 2, round two right turns through 6 and 7 costs 1.9
+        for (from, to, weight) in [
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (0, 3, 0.3),
+            (3, 4, 1.3),
+            (4, 2, 0.3),
+        ] {
+            graph.add_edge(nodes[from], nodes[to], Edge::new(nodes[to].0, weight));
+            graph.add_edge(nodes[to], nodes[from], Edge::new(nodes[from].0, weight));
+        }
+
+        assert_eq!(graph.find_shortest_path(1, 3).node_ids(), vec![1, 6, 7, 3]);
+        assert_eq!(
+            graph.find_shortest_path_with_turn_costs(1, 3).node_ids(),
+            vec![1, 2, 3]
+        );
+
+        graph.set_turn_costs(TurnCostModel::free());
+        assert_eq!(
+            graph.find_shortest_path_with_turn_costs(1, 3).node_ids(),
+            vec![1, 6, 7, 3]
+        );
+    }
+
+    #[test]
+    fn test_u_turns() {
+        let mut graph = loop_junction();
+
+        // turning back at 2 is the shortest way round the restriction
+        assert_eq!(
+            graph.find_shortest_path_turn_aware(1, 3).node_ids(),
+            vec![1, 5, 2, 5, 3]
+        );
+        // but a minute of U-turn is worth the loop
+        assert_eq!(*graph.turn_costs(), Profile::car().turn_costs);
+        assert_eq!(
+            graph.find_shortest_path_with_turn_costs(1, 3).node_ids(),
+            vec![1, 5, 2, 6, 3]
+        );
+
+        graph.set_turn_costs(TurnCostModel {
+            u_turn: UTurn::Allowed,
+            ..Default::default()
+        });
+        assert_eq!(
+            graph.find_shortest_path_with_turn_costs(1, 3).node_ids(),
+            vec![1, 5, 2, 5, 3]
+        );
+
+        graph.set_turn_costs(TurnCostModel {
+            u_turn: UTurn::Forbidden,
+            ..TurnCostModel::free()
+        });
+        assert_eq!(
+            graph.find_shortest_path_with_turn_costs(1, 3).node_ids(),
+            vec![1, 5, 2, 6, 3]
+        );
+    }
+
+    #[test]
+    fn test_search_with_turn_costs() {
+        let mut graph = loop_junction();
+
+        for metric in [Metric::Shortest, Metric::Fastest] {
+            graph.build_landmarks(2, LandmarkStrategy::Farthest, metric);
+            for algorithm in TURN_AWARE {
+                let options = SearchOptions {
+                    algorithm,
+                    metric,
+                    turn_costs: true,
+                    ..Default::default()
+                };
+                assert_eq!(
+                    graph.search(1, 3, &options).unwrap().route.node_ids(),
+                    vec![1, 5, 2, 6, 3],
+                    "{}",
+                    algorithm
+                );
+            }
+        }
+
+        // the route costs what was minimised, turn penalties included
+        for metric in [Metric::Shortest, Metric::Fastest] {
+            let plain = graph
+                .search(
+                    1,
+                    3,
+                    &SearchOptions {
+                        metric,
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+                .route;
+            let options = SearchOptions {
+                metric,
+                turn_costs: true,
+                ..Default::default()
+            };
+            let route = graph.search(1, 3, &options).unwrap().route;
+            assert_eq!(plain.turn_penalty, 0.0);
+            assert!(route.turn_penalty > 0.0);
+            let edges = graph.to_route(&route.path(), metric).cost;
+            assert!((route.cost - route.turn_penalty - edges).abs() < 1e-3);
+
+            // turning back is cheaper on the edges, but not once its penalty is paid
+            let mut penalised = plain.clone();
+            graph.add_turn_penalties(&mut penalised);
+            assert!(plain.cost < route.cost - route.turn_penalty);
+            assert!(route.cost < penalised.cost);
+        }
+
+        // turn costs still apply without the restrictions
+        let options = SearchOptions {
+            turn_costs: true,
+            ignore_turn_restrictions: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph.search(1, 3, &options).unwrap().route.node_ids(),
+            vec![1, 5, 3]
+        );
+        for algorithm in NODE_BASED {
+            let options = SearchOptions {
+                algorithm,
+                ..options
+            };
+            assert_eq!(
+                graph.search(1, 3, &options),
+                Err(PathError::UnsupportedWithRestrictions(algorithm))
+            );
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+
+/// Which outline to draw around a set of points
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HullKind {
+    /// No outline
+    None,
+    /// The smallest convex polygon holding every point
+    #[default]
+    Convex,
+    /// Follows gaps between the points more closely, see `concave_hull`
+    Concave,
+}
+
+/// Below this the concave hull stops digging into an edge, higher values give smoother outlines
+pub const DEFAULT_CONCAVITY: f64 = 2.0;
+
+/// Counter clockwise convex hull of the `(lat, lon)` points, without repeating the first point at
+/// the end
+/// Returns the distinct points when there are fewer than 3 of them or they are all on one line
+pub fn convex_hull(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let projection = Projection::new(points);
+    convex_hull_indices(&projection.project_all(points))
+        .into_iter()
+        .map(|i| points[i])
+        .collect()
+}
+
+/// Counter clockwise concave hull of the points, starting from the convex hull and digging an edge
+/// in towards the nearest point inside while the edge is longer than `concavity` times the distance
+/// to that point, after Park and Oh, 2012
+pub fn concave_hull(points: &[(f64, f64)], concavity: f64) -> Vec<(f64, f64)> {
+    let projection = Projection::new(points);
+    let projected = projection.project_all(points);
+    let mut hull = convex_hull_indices(&projected);
+    if hull.len() < 3 {
+        return hull.into_iter().map(|i| points[i]).collect();
+    }
+
+    let mut inside: Vec<bool> = vec![true; points.len()];
+    for &i in &hull {
+        inside[i] = false;
+    }
+    // duplicates of hull points can't be dug to
+    for (i, point) in projected.iter().enumerate() {
+        if hull.iter().any(|&h| projected[h] == *point) {
+            inside[i] = false;
+        }
+    }
+
+    let mut edge = 0;
+    while edge < hull.len() {
+        let (a, b) = (
+            projected[hull[edge]],
+            projected[hull[(edge + 1) % hull.len()]],
+        );
+        let length = distance(a, b);
+
+        let nearest = (0..points.len())
+            .filter(|&i| inside[i])
+            .map(|i| (i, segment_distance(projected[i], a, b)))
+            .min_by(|x, y| x.1.total_cmp(&y.1));
+
+        let dig = nearest.filter(|&(i, _)| {
+            let p = projected[i];
+            let closest_end = distance(p, a).min(distance(p, b));
+            closest_end > 0.0
+                && length / closest_end > concavity
+                && !crosses_hull(&projected, &hull, edge, a, p)
+                && !crosses_hull(&projected, &hull, edge, p, b)
+        });
+
+        match dig {
+            Some((i, _)) => {
+                hull.insert(edge + 1, i);
+                inside[i] = false;
+            }
+            None => edge += 1,
+        }
+    }
+
+    hull.into_iter().map(|i| points[i]).collect()
+}
+
+/// Scales longitudes by the cosine of the mean latitude so distances are about the same in both
+/// directions
+struct Projection {
+    lon_scale: f64,
+}
+
+impl Projection {
+    fn new(points: &[(f64, f64)]) -> Self {
+        let mean_lat = points.iter().map(|point| point.0).sum::<f64>() / points.len().max(1) as f64;
+        Projection {
+            lon_scale: mean_lat.to_radians().cos(),
+        }
+    }
+
+    /// `(x, y)` for each `(lat, lon)`
+    fn project_all(&self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        points
+            .iter()
+            .map(|&(lat, lon)| (lon * self.lon_scale, lat))
+            .collect()
+    }
+}
+
+/// Andrew's monotone chain, returns indices into the points
+fn convex_hull_indices(points: &[(f64, f64)]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&i, &j| {
+        points[i]
+            .0
+            .total_cmp(&points[j].0)
+            .then(points[i].1.total_cmp(&points[j].1))
+    });
+    order.dedup_by(|i, j| points[*i] == points[*j]);
+    if order.len() < 3 {
+        return order;
+    }
+
+    let mut hull: Vec<usize> = Vec::with_capacity(order.len() * 2);
+    for pass in [order.clone(), order.iter().rev().copied().collect()] {
+        let floor = hull.len();
+        for i in pass {
+            while hull.len() >= floor + 2
+                && cross(
+                    points[hull[hull.len() - 2]],
+                    points[hull[hull.len() - 1]],
+                    points[i],
+                ) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(i);
+        }
+        // the last point of each chain is the first of the other
+        hull.pop();
+    }
+
+    if hull.len() < 3 {
+        // all on one line
+        return vec![order[0], order[order.len() - 1]];
+    }
+    hull
+}
+
+/// Whether the segment from `p` to `q` crosses any hull edge but the one at `skip` and its
+/// neighbours
+fn crosses_hull(
+    points: &[(f64, f64)],
+    hull: &[usize],
+    skip: usize,
+    p: (f64, f64),
+    q: (f64, f64),
+) -> bool {
+    let n = hull.len();
+    (0..n)
+        .filter(|&edge| edge != skip && edge != (skip + 1) % n && edge != (skip + n - 1) % n)
+        .any(|edge| segments_cross(p, q, points[hull[edge]], points[hull[(edge + 1) % n]]))
+}
+
+/// Proper crossing of two segments, touching at an end doesn't count
+fn segments_cross(p1: (f64, f64), p2: (f64, f64), q1: (f64, f64), q2: (f64, f64)) -> bool {
+    let d1 = cross(q1, q2, p1);
+    let d2 = cross(q1, q2, p2);
+    let d3 = cross(p1, p2, q1);
+    let d4 = cross(p1, p2, q2);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Positive when `o`, `a`, `b` turn counter clockwise
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_2 = dx * dx + dy * dy;
+    if length_2 == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_2).clamp(0.0, 1.0);
+    distance(p, (a.0 + t * dx, a.1 + t * dy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{concave_hull, convex_hull, Projection};
+
+    /// Twice the signed area on the projection, positive when counter clockwise
+    fn area(polygon: &[(f64, f64)]) -> f64 {
+        let projected = Projection::new(polygon).project_all(polygon);
+        (0..projected.len())
+            .map(|i| {
+                let (a, b) = (projected[i], projected[(i + 1) % projected.len()]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum()
+    }
+
+    /// Points on and inside the outline of a U
+    fn u_shape() -> Vec<(f64, f64)> {
+        let mut points = Vec::new();
+        for i in 0..=10 {
+            let step = i as f64 * 0.001;
+            // the two arms and the base
+            points.push((50.0 + step, 0.0));
+            points.push((50.0 + step, 0.002));
+            points.push((50.0 + step, 0.008));
+            points.push((50.0 + step, 0.01));
+            points.push((50.0, step));
+            points.push((50.002, step));
+        }
+        points
+    }
+
+    #[test]
+    fn test_convex_hull() {
+        let points = vec![
+            (50.0, 0.0),
+            (50.0, 1.0),
+            (51.0, 1.0),
+            (51.0, 0.0),
+            (50.5, 0.5),
+            (50.2, 0.7),
+        ];
+
+        let hull = convex_hull(&points);
+
+        assert_eq!(hull.len(), 4);
+        assert!(!hull.contains(&(50.5, 0.5)));
+        assert!(area(&hull) > 0.0);
+    }
+
+    #[test]
+    fn test_convex_hull_degenerate() {
+        assert!(convex_hull(&[]).is_empty());
+        assert_eq!(convex_hull(&[(50.0, 0.0), (50.0, 0.0)]), vec![(50.0, 0.0)]);
+        assert_eq!(
+            convex_hull(&[(50.0, 0.0), (50.5, 0.0), (51.0, 0.0)]),
+            vec![(50.0, 0.0), (51.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_concave_hull_follows_the_gap() {
+        let points = u_shape();
+
+        let convex = convex_hull(&points);
+        let concave = concave_hull(&points, 2.0);
+
+        assert!(concave.len() > convex.len());
+        assert!(area(&concave) > 0.0);
+        assert!(area(&concave) < area(&convex) * 0.8);
+        // every convex hull vertex is still on the concave hull
+        assert!(convex.iter().all(|point| concave.contains(point)));
+    }
+
+    #[test]
+    fn test_concave_hull_huge_concavity_is_convex() {
+        let points = u_shape();
+
+        assert_eq!(
+            concave_hull(&points, f64::INFINITY).len(),
+            convex_hull(&points).len()
+        );
+    }
+}
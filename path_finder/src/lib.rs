@@ -1,16 +1,47 @@
+pub mod algorithm;
+pub mod error;
+pub mod export;
 pub mod graph;
-pub mod r_tree;
+pub mod hull;
+pub mod metric;
 pub(crate) mod osm_data;
+pub mod polyline;
+pub mod profile;
+pub mod r_tree;
+pub mod restriction;
+pub mod search;
+pub mod speed;
+pub mod trace;
+pub mod turn_cost;
+pub mod way_filter;
 
-pub use graph::Graph;
+pub use algorithm::Algorithm;
+pub use error::PathError;
+pub use export::ExportFormat;
+pub use graph::{
+    ContractionHierarchy, Directions, DistanceMatrix, EdgeSnap, Graph, Instruction, Isochrone,
+    IsochroneBand, LandmarkStrategy, Landmarks, Leg, Maneuver, MultiStopRoute, NetworkEdge,
+    PointPath, ReachableNode, Route, RouteEdge, RouteNode, SubNetwork, Tour, WayInfo,
+};
+pub use hull::HullKind;
+pub use metric::Metric;
+pub use profile::{Oneway, Profile};
+pub use restriction::{RestrictionKind, TurnRestriction};
+pub use search::{AlternativeOptions, IsochroneOptions, SearchOptions, SearchResult};
+pub use turn_cost::{TurnCostModel, UTurn};
+pub use way_filter::WayFilter;
 
 use lazy_static::lazy_static;
 use osm_data::OSMData;
+use serde::Serialize;
 use std::sync::Mutex;
 use wasm_bindgen::prelude::*;
 
 lazy_static! {
     static ref GRAPH: Mutex<Option<Graph>> = Mutex::new(None);
+    /// Kept so the graph can be rebuilt when the profile changes
+    static ref OSM_DATA: Mutex<Option<OSMData>> = Mutex::new(None);
+    static ref PROFILE: Mutex<Profile> = Mutex::new(Profile::default());
 }
 
 #[wasm_bindgen]
@@ -24,6 +55,7 @@ pub fn initialize() {
     log("WASM Initialized!");
 }
 
+/// Loads the graph for the current profile
 #[wasm_bindgen]
 pub fn load_graph(json_data: &str) -> Result<(), JsValue> {
     log("Loading Graph from JSON...");
@@ -32,8 +64,124 @@ pub fn load_graph(json_data: &str) -> Result<(), JsValue> {
         log("Invalid JSON format.");
         JsValue::from_str(&format!("Invalid JSON: {}", e))
     })?;
+    *OSM_DATA.lock().unwrap() = Some(osm_data);
+
+    build_graph()
+}
+
+/// Loads a graph saved by `grapher ch`, with its contraction hierarchy already built
+/// The current profile becomes the one the graph was built for, with the turn costs saved in the
+/// graph
+/// Profile changes don't rebuild it, as there is no OSM data to rebuild from
+#[wasm_bindgen]
+pub fn load_contracted_graph(json_data: &str) -> Result<(), JsValue> {
+    log("Loading contracted Graph from JSON...");
+
+    let graph: Graph = serde_json::from_str(json_data).map_err(|e| {
+        log("Invalid JSON format.");
+        JsValue::from_str(&format!("Invalid JSON: {}", e))
+    })?;
+    if graph.contraction_hierarchy().is_none() {
+        log("The graph has no contraction hierarchy.");
+    }
+    match graph.profile_name().parse::<Profile>() {
+        Ok(mut profile) => {
+            profile.turn_costs = *graph.turn_costs();
+            *PROFILE.lock().unwrap() = profile;
+        }
+        Err(_) => log(&format!(
+            "Unknown profile {:?}, keeping the current one.",
+            graph.profile_name()
+        )),
+    }
+
+    *OSM_DATA.lock().unwrap() = None;
+    *GRAPH.lock().unwrap() = Some(graph);
+    Ok(())
+}
+
+/// Loads the graph keeping only the ways whose `highway` value is in `highways`
+#[wasm_bindgen]
+pub fn load_graph_with_highways(json_data: &str, highways: Vec<String>) -> Result<(), JsValue> {
+    log(&format!("Using highways {:?}", highways));
+
+    {
+        let mut profile = PROFILE.lock().unwrap();
+        profile.way_filter = WayFilter::new(highways);
+    }
+    load_graph(json_data)
+}
+
+/// Switches to one of the `default`, `car`, `bicycle` or `foot` profiles
+/// The loaded graph is rebuilt for the new profile
+#[wasm_bindgen]
+pub fn set_profile(name: &str) -> Result<(), JsValue> {
+    let profile: Profile = name
+        .parse()
+        .map_err(|e: PathError| JsValue::from_str(&e.to_string()))?;
+    *PROFILE.lock().unwrap() = profile;
+
+    if OSM_DATA.lock().unwrap().is_some() {
+        build_graph()?;
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn get_profile() -> String {
+    PROFILE.lock().unwrap().name.clone()
+}
+
+/// Replaces the turn penalties of the current profile and the loaded graph
+/// `turn_costs` is `{ straight_angle, left_turn_penalty, right_turn_penalty, u_turn }`, every field
+/// is optional, with penalties in seconds and `u_turn` one of `allowed`, `forbidden` or
+/// `{ penalty: seconds }`
+/// They are applied to searches with `turn_costs: true`
+#[wasm_bindgen]
+pub fn set_turn_costs(turn_costs: JsValue) -> Result<(), JsValue> {
+    let turn_costs: TurnCostModel = serde_wasm_bindgen::from_value(turn_costs)
+        .map_err(|e| JsValue::from_str(&format!("Invalid turn costs: {}", e)))?;
+    PROFILE.lock().unwrap().turn_costs = turn_costs;
+
+    if let Some(graph) = GRAPH.lock().unwrap().as_mut() {
+        graph.set_turn_costs(turn_costs);
+    }
+    Ok(())
+}
+
+/// Picks `count` landmarks for the `alt` algorithm on the loaded graph
+/// `strategy` is `farthest` or `avoid` and `metric` is the metric they are used with
+#[wasm_bindgen]
+pub fn build_landmarks(count: usize, strategy: &str, metric: &str) -> Result<(), JsValue> {
+    let strategy: LandmarkStrategy = strategy
+        .parse()
+        .map_err(|e: PathError| JsValue::from_str(&e.to_string()))?;
+    let metric: Metric = metric
+        .parse()
+        .map_err(|e: PathError| JsValue::from_str(&e.to_string()))?;
+
+    let mut g = GRAPH.lock().unwrap();
+    let graph = g
+        .as_mut()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    graph.build_landmarks(count, strategy, metric);
+
+    log(&format!(
+        "Built {} landmarks with the {} strategy",
+        count, strategy
+    ));
+    Ok(())
+}
 
-    let graph = Graph::from_osm_data(osm_data).map_err(|e| {
+/// Builds the graph from the loaded OSM data with the current profile
+fn build_graph() -> Result<(), JsValue> {
+    let osm_data = OSM_DATA.lock().unwrap();
+    let osm_data = osm_data
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No OSM data loaded"))?;
+    let profile = PROFILE.lock().unwrap();
+
+    let graph = Graph::from_osm_data_with_profile(osm_data, &profile).map_err(|e| {
         log("Graph creation failed.");
         JsValue::from_str(&format!("Graph creation failed: {}", e))
     })?;
@@ -41,33 +189,404 @@ pub fn load_graph(json_data: &str) -> Result<(), JsValue> {
     let mut g = GRAPH.lock().unwrap();
     *g = Some(graph);
 
-    log("Graph successfully loaded into memory!");
+    log(&format!(
+        "Graph successfully loaded into memory with the {} profile!",
+        profile
+    ));
     Ok(())
 }
 
+/// Finds the path between the nodes nearest to the two points
+/// `options` is `{ algorithm, metric, trace, ignore_turn_restrictions, turn_costs, polyline }`,
+/// every field is optional:
+/// `algorithm` is one of `dijkstra`, `a_star`, `bellman_ford`, `bidirectional`, `alt` or
+/// `contraction_hierarchy`,
+/// `metric` is `shortest` or `fastest`, `trace` records the exploration order
+/// and `turn_costs` adds the turn penalties of the profile, `polyline` set to 5 or 6 adds the route
+/// geometry as a polyline encoded at that precision
+/// The chosen algorithm always runs: `dijkstra`, `a_star` and `alt` search edge by edge to apply
+/// turn restrictions and turn costs, the others return an error when the graph has restrictions
+/// that aren't ignored or turn costs are on
+/// Returns `{ route, trace }` where `route` is
+/// `{ metric, cost, turn_penalty, distance, duration, nodes, edges }` with the `cost` including the
+/// `turn_penalty`, the `distance` in metres, `nodes` as `{ id, lat, lon }`, `edges` as
+/// `{ from, to, way_id, highway, distance, duration, speed }` and `polyline` the encoded geometry
+/// or `null`
 #[wasm_bindgen]
-pub fn find_shortest_path(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> JsValue {
+pub fn find_shortest_path(
+    lat1: f64,
+    lon1: f64,
+    lat2: f64,
+    lon2: f64,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options: SearchOptions = options_from_js(options)?;
+
     let g = GRAPH.lock().expect("Failed to lock GRAPH");
     if let Some(ref graph) = *g {
-        let start_node = graph.nearest_neighbor(lat1, lon1);
-        let end_node = graph.nearest_neighbor(lat2, lon2);
+        if let Some((start_node, end_node)) = nearest_nodes(graph, lat1, lon1, lat2, lon2) {
+            log(&format!(
+                "start: {} / end: {} / algorithm: {} / metric: {}",
+                start_node, end_node, options.algorithm, options.metric
+            ));
 
-        if let (Some(start_node), Some(end_node)) = (start_node, end_node) {
-            if !graph.contains_node_id(start_node) || !graph.contains_node_id(end_node) {
-                return JsValue::NULL;
-            }
+            let result = graph
+                .search(start_node, end_node, &options)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-            log(&format!("start: {} / end: {}", start_node, end_node));
+            log(&format!("path: {:?}", result.route.node_ids()));
 
-            let path = graph.find_shortest_path(start_node, end_node);
-            
-            log(&format!("path: {:?}", path));
+            // Convert the result to a JS object
+            return Ok(serde_wasm_bindgen::to_value(&result).unwrap());
+        }
+    }
+    Ok(JsValue::NULL)
+}
+
+/// Finds the cheapest path between the two points projected onto their nearest road segments,
+/// so a click in the middle of a long road starts there rather than at a junction far away
+/// `metric` is `shortest` (the default) or `fastest`, and turn restrictions are followed unless
+/// `ignore_turn_restrictions` is true
+/// Returns `{ start, end, nodes, geometry, cost, distance }` where `geometry` is `[lat, lon]` pairs
+/// from the start projection to the end projection and `distance` is in metres, or `null` when the
+/// end can't be reached
+#[wasm_bindgen]
+pub fn find_path_between_points(
+    lat1: f64,
+    lon1: f64,
+    lat2: f64,
+    lon2: f64,
+    metric: Option<String>,
+    ignore_turn_restrictions: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let metric: Metric = metric
+        .map_or(Ok(Metric::default()), |metric| metric.parse())
+        .map_err(|e: PathError| JsValue::from_str(&e.to_string()))?;
 
-            // Convert Vec<(id, lat, lon)> to JSON for JS usage
-            return serde_wasm_bindgen::to_value(&path).unwrap();
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let path = graph
+        .route_between_points(
+            (lat1, lon1),
+            (lat2, lon2),
+            metric,
+            ignore_turn_restrictions.unwrap_or(false),
+        )
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    match path {
+        Some(path) => {
+            log(&format!(
+                "path between points: {:?}",
+                path.nodes.iter().map(|node| node.id).collect::<Vec<_>>()
+            ));
+            Ok(serde_wasm_bindgen::to_value(&path).unwrap())
         }
-    } 
-    JsValue::NULL
+        None => Ok(JsValue::NULL),
+    }
+}
+
+/// Finds the path between the nodes nearest to the two points with turn by turn instructions
+/// `options` is the same as for `find_shortest_path`
+/// Returns `{ route, instructions }` with the route as from `find_shortest_path` and each
+/// instruction as `{ maneuver: { type, exit? }, node, road, distance, duration, text }`,
+/// or `null` when no node is near
+#[wasm_bindgen]
+pub fn find_directions(
+    lat1: f64,
+    lon1: f64,
+    lat2: f64,
+    lon2: f64,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options: SearchOptions = options_from_js(options)?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let Some((start_node, end_node)) = nearest_nodes(graph, lat1, lon1, lat2, lon2) else {
+        return Ok(JsValue::NULL);
+    };
+
+    let directions = graph
+        .directions(start_node, end_node, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    log(&format!(
+        "{} instructions from {} to {}",
+        directions.instructions.len(),
+        start_node,
+        end_node
+    ));
+    Ok(serde_wasm_bindgen::to_value(&directions).unwrap())
+}
+
+/// Finds the path between the nodes nearest to the two points and writes it out for other tools,
+/// like a download button
+/// `format` is `geojson`, `gpx`, `gpx_route` or `kml` and `options` the same as for
+/// `find_shortest_path`
+/// Returns the content of the file, or `null` when no node is near
+#[wasm_bindgen]
+pub fn export_route(
+    lat1: f64,
+    lon1: f64,
+    lat2: f64,
+    lon2: f64,
+    format: String,
+    name: Option<String>,
+    options: JsValue,
+) -> Result<Option<String>, JsValue> {
+    let format: ExportFormat = format
+        .parse()
+        .map_err(|e: PathError| JsValue::from_str(&e.to_string()))?;
+    let options: SearchOptions = options_from_js(options)?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let Some((start_node, end_node)) = nearest_nodes(graph, lat1, lon1, lat2, lon2) else {
+        return Ok(None);
+    };
+
+    let route = graph
+        .search(start_node, end_node, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?
+        .route;
+
+    log(&format!(
+        "exporting {} nodes from {} to {} as {}",
+        route.nodes.len(),
+        start_node,
+        end_node,
+        format
+    ));
+    Ok(Some(
+        route.export(format, name.as_deref().unwrap_or("Route")),
+    ))
+}
+
+/// Finds the shortest path and a few meaningfully different alternatives between the nodes nearest
+/// to the two points
+/// `options` is `{ metric, count, max_overlap, candidates, ignore_turn_restrictions }`, every field
+/// is optional
+/// Routes taking a turn forbidden by a turn restriction are left out unless
+/// `ignore_turn_restrictions` is set
+/// Returns an array of routes as from `find_shortest_path`, shortest first, which is empty when the
+/// points aren't connected, or an error when no graph is loaded
+#[wasm_bindgen]
+pub fn find_alternative_routes(
+    lat1: f64,
+    lon1: f64,
+    lat2: f64,
+    lon2: f64,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options: AlternativeOptions = options_from_js(options)?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let nodes = graph
+        .snap_points(&[(lat1, lon1), (lat2, lon2)])
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let (start_node, end_node) = (nodes[0], nodes[1]);
+
+    let routes = graph.alternative_routes(start_node, end_node, &options);
+    log(&format!(
+        "found {} routes from {} to {}",
+        routes.len(),
+        start_node,
+        end_node
+    ));
+
+    Ok(serde_wasm_bindgen::to_value(&routes).unwrap())
+}
+
+/// Routes through the nodes nearest to each waypoint in order
+/// `waypoints` is an array of `[lat, lon]` pairs and `options` the same as for `find_shortest_path`
+/// Returns `{ route, legs }` with the `distance` in metres and `duration` in seconds of every leg,
+/// or an error naming the leg that can't be routed
+#[wasm_bindgen]
+pub fn find_route_through(waypoints: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    let waypoints: Vec<(f64, f64)> = serde_wasm_bindgen::from_value(waypoints)
+        .map_err(|e| JsValue::from_str(&format!("Invalid waypoints: {}", e)))?;
+    let options: SearchOptions = options_from_js(options)?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let route = graph
+        .route_through_points(&waypoints, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    log(&format!(
+        "route through {} waypoints: {:?}",
+        waypoints.len(),
+        route.legs
+    ));
+    Ok(serde_wasm_bindgen::to_value(&route).unwrap())
+}
+
+/// Orders the stops of a delivery style tour and routes it
+/// `points` is an array of `[lat, lon]` pairs: the start, then the stops, then the end when
+/// `fixed_end` is set
+/// `options` is the same as for `find_shortest_path`
+/// Returns `{ order, waypoints, cost, route }` where `order` indexes the stops in visiting order
+#[wasm_bindgen]
+pub fn plan_tour(points: JsValue, fixed_end: bool, options: JsValue) -> Result<JsValue, JsValue> {
+    let points: Vec<(f64, f64)> = serde_wasm_bindgen::from_value(points)
+        .map_err(|e| JsValue::from_str(&format!("Invalid points: {}", e)))?;
+    let options: SearchOptions = options_from_js(options)?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let nodes = graph
+        .snap_points(&points)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let (&start, rest) = nodes
+        .split_first()
+        .ok_or_else(|| JsValue::from_str(&PathError::TooFewWaypoints(0).to_string()))?;
+    let (stops, end) = match rest.split_last() {
+        Some((&end, stops)) if fixed_end => (stops, Some(end)),
+        _ => (rest, None),
+    };
+
+    let tour = graph
+        .plan_tour(start, stops, end, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    log(&format!(
+        "tour through {} stops: {:?}",
+        stops.len(),
+        tour.waypoints
+    ));
+    Ok(serde_wasm_bindgen::to_value(&tour).unwrap())
+}
+
+/// Costs from the nodes nearest to each source to the nodes nearest to each target, one search per
+/// source
+/// `sources` and `targets` are arrays of `[lat, lon]` pairs and `metric` is `shortest` (the
+/// default) or `fastest`
+/// Returns `{ metric, sources, targets, costs }` where `costs[i][j]` is `null` when target `j`
+/// can't be reached from source `i`
+#[wasm_bindgen]
+pub fn distance_matrix(
+    sources: JsValue,
+    targets: JsValue,
+    metric: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let sources: Vec<(f64, f64)> = serde_wasm_bindgen::from_value(sources)
+        .map_err(|e| JsValue::from_str(&format!("Invalid sources: {}", e)))?;
+    let targets: Vec<(f64, f64)> = serde_wasm_bindgen::from_value(targets)
+        .map_err(|e| JsValue::from_str(&format!("Invalid targets: {}", e)))?;
+    let metric: Metric = metric
+        .map_or(Ok(Metric::default()), |metric| metric.parse())
+        .map_err(|e: PathError| JsValue::from_str(&e.to_string()))?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let sources = graph
+        .snap_points(&sources)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let targets = graph
+        .snap_points(&targets)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let matrix = graph.distance_matrix_with(&sources, &targets, metric);
+    Ok(serde_wasm_bindgen::to_value(&matrix).unwrap())
+}
+
+/// Nodes reachable from the node nearest to the point within each threshold of the options
+/// `options` is `{ metric, thresholds, hull, concavity }`, see `IsochroneOptions`
+/// Returns `{ origin, metric, nodes, bands, geojson }` where `geojson` is a `FeatureCollection`
+/// with a polygon per band
+#[wasm_bindgen]
+pub fn find_isochrone(lat: f64, lon: f64, options: JsValue) -> Result<JsValue, JsValue> {
+    let options: IsochroneOptions = options_from_js(options)?;
+
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+    let isochrone = graph
+        .isochrone_from_point(lat, lon, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    log(&format!(
+        "isochrone from {} reaches {} nodes",
+        isochrone.origin,
+        isochrone.nodes.len()
+    ));
+    let mut value = serde_json::to_value(&isochrone).unwrap();
+    value["geojson"] = isochrone.to_geojson();
+    Ok(value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .unwrap())
+}
+
+/// The nodes inside the box and the edges between them, for drawing the visible part of the network
+/// Returns `{ nodes, edges }` with the nodes as `[id, lat, lon]` and the edges as
+/// `{ from, to, distance, duration, way_id }`, the `distance` in metres and `duration` in seconds
+#[wasm_bindgen]
+pub fn network_in_bbox(
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+) -> Result<JsValue, JsValue> {
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+
+    let network = graph.network_in_bbox(min_lat, min_lon, max_lat, max_lon);
+    Ok(serde_wasm_bindgen::to_value(&network).unwrap())
+}
+
+/// The nodes within `radius` metres of the point and the edges between them, the same shape as
+/// `network_in_bbox`
+#[wasm_bindgen]
+pub fn network_within_radius(lat: f64, lon: f64, radius: f64) -> Result<JsValue, JsValue> {
+    let g = GRAPH.lock().expect("Failed to lock GRAPH");
+    let graph = g
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("No graph loaded"))?;
+
+    let network = graph.network_within_radius(lat, lon, radius);
+    Ok(serde_wasm_bindgen::to_value(&network).unwrap())
+}
+
+/// Missing options use the defaults
+fn options_from_js<T>(options: JsValue) -> Result<T, JsValue>
+where
+    T: Default + serde::de::DeserializeOwned,
+{
+    if options.is_undefined() || options.is_null() {
+        Ok(T::default())
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))
+    }
+}
+
+/// The graph nodes nearest to the two points
+fn nearest_nodes(graph: &Graph, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Option<(u64, u64)> {
+    let start_node = graph.nearest_neighbor(lat1, lon1)?;
+    let end_node = graph.nearest_neighbor(lat2, lon2)?;
+
+    (graph.contains_node_id(start_node) && graph.contains_node_id(end_node))
+        .then_some((start_node, end_node))
 }
 
 #[wasm_bindgen]
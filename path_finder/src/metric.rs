@@ -0,0 +1,53 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::PathError;
+
+/// What a search minimises
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// The length of the path, adjusted by the profile preferences
+    #[default]
+    Shortest,
+    /// The estimated travel time of the path in seconds
+    Fastest,
+}
+
+impl FromStr for Metric {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shortest" => Ok(Metric::Shortest),
+            "fastest" => Ok(Metric::Fastest),
+            other => Err(PathError::UnknownMetric(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Metric::Shortest => "shortest",
+            Metric::Fastest => "fastest",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metric() {
+        assert_eq!("fastest".parse::<Metric>(), Ok(Metric::Fastest));
+        assert_eq!("shortest".parse::<Metric>(), Ok(Metric::Shortest));
+        assert_eq!(
+            "scenic".parse::<Metric>(),
+            Err(PathError::UnknownMetric("scenic".to_string()))
+        );
+    }
+}
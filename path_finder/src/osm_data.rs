@@ -19,9 +19,27 @@ pub(crate) struct Way {
     pub tags: HashMap<String, String>,
 }
 
+/// Represents a `member` of a relation, `_type` is `node`, `way` or `relation`
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RelationMember {
+    pub _type: String,
+    pub ref_id: u64,
+    pub role: String,
+}
+
+/// Represents an OSM relation, like a turn restriction, grouping nodes and ways with roles
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Relation {
+    pub id: u64,
+    pub members: Vec<RelationMember>,
+    pub tags: HashMap<String, String>,
+}
+
 /// Represents the full parsed OSM JSON structure
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct OSMData {
     pub nodes: Vec<Node>,
     pub ways: Vec<Way>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
 }
@@ -0,0 +1,198 @@
+use crate::error::PathError;
+
+/// Precision of Google's encoded polylines, 5 decimal places or about a metre
+pub const GOOGLE_PRECISION: u32 = 5;
+/// Precision used by OSRM and Valhalla, 6 decimal places or about 10 centimetres
+pub const OSRM_PRECISION: u32 = 6;
+
+/// Encodes the `(lat, lon)` points with the polyline algorithm, rounding them to `precision`
+/// decimal places
+/// See https://developers.google.com/maps/documentation/utilities/polylinealgorithm
+pub fn encode(points: &[(f64, f64)], precision: u32) -> String {
+    let factor = 10f64.powi(precision as i32);
+    let mut encoded = String::new();
+    let (mut last_lat, mut last_lon) = (0i64, 0i64);
+
+    for &(lat, lon) in points {
+        let (lat, lon) = ((lat * factor).round() as i64, (lon * factor).round() as i64);
+        encode_value(lat - last_lat, &mut encoded);
+        encode_value(lon - last_lon, &mut encoded);
+        (last_lat, last_lon) = (lat, lon);
+    }
+    encoded
+}
+
+/// Decodes a polyline encoded at `precision` back into `(lat, lon)` points
+/// Fails with the byte offset of the first value that isn't valid, like a truncated one, a latitude
+/// without a longitude or one that takes the coordinate out of range
+pub fn decode(encoded: &str, precision: u32) -> Result<Vec<(f64, f64)>, PathError> {
+    let factor = 10f64.powi(precision as i32);
+    let bytes = encoded.as_bytes();
+    let mut points = Vec::new();
+    let (mut lat, mut lon) = (0i64, 0i64);
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let start = index;
+        let mut next = |value: i64| {
+            decode_value(bytes, &mut index)
+                .and_then(|delta| value.checked_add(delta))
+                .ok_or(PathError::InvalidPolyline(start))
+        };
+        lat = next(lat)?;
+        lon = next(lon)?;
+        points.push((lat as f64 / factor, lon as f64 / factor));
+    }
+    Ok(points)
+}
+
+/// Appends the zigzag encoded value in 5 bit chunks, least significant first
+fn encode_value(value: i64, encoded: &mut String) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x20 {
+        encoded.push((((value & 0x1f) | 0x20) as u8 + 63) as char);
+        value >>= 5;
+    }
+    encoded.push((value as u8 + 63) as char);
+}
+
+/// Reads one value starting at `index` and moves past it, `None` when it is cut short or has a
+/// character outside of the polyline alphabet
+fn decode_value(bytes: &[u8], index: &mut usize) -> Option<i64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let chunk = bytes
+            .get(*index)?
+            .checked_sub(63)
+            .filter(|&chunk| chunk < 64 && shift < 64)? as u64;
+        *index += 1;
+        value |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    Some(((value >> 1) as i64) ^ -((value & 1) as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_reference() {
+        // the example of the algorithm description
+        let points = [(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)];
+
+        assert_eq!(
+            encode(&points, GOOGLE_PRECISION),
+            "_p~iF~ps|U_ulLnnqC_mqNvxq`@"
+        );
+        assert_eq!(
+            decode("_p~iF~ps|U_ulLnnqC_mqNvxq`@", GOOGLE_PRECISION),
+            Ok(points.to_vec())
+        );
+        assert_eq!(encode(&[], GOOGLE_PRECISION), "");
+        assert_eq!(decode("", OSRM_PRECISION), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_round_trip_precision() {
+        // points all over the globe with many decimals, both signs and large jumps between them
+        let points: Vec<(f64, f64)> = (0..500)
+            .map(|i| {
+                let t = i as f64;
+                (
+                    (t * 0.731_234_567).sin() * 89.999_999,
+                    (t * 1.379_876_543).cos() * 179.999_999,
+                )
+            })
+            .collect();
+
+        for precision in [GOOGLE_PRECISION, OSRM_PRECISION] {
+            let decoded = decode(&encode(&points, precision), precision).unwrap();
+            let bound = 0.5 / 10f64.powi(precision as i32) + 1e-12;
+
+            assert_eq!(decoded.len(), points.len());
+            for (point, decoded) in points.iter().zip(&decoded) {
+                assert!(
+                    (point.0 - decoded.0).abs() <= bound,
+                    "{:?} {:?} at precision {}",
+                    point,
+                    decoded,
+                    precision
+                );
+                assert!(
+                    (point.1 - decoded.1).abs() <= bound,
+                    "{:?} {:?} at precision {}",
+                    point,
+                    decoded,
+                    precision
+                );
+            }
+            // already rounded points come back unchanged
+            assert_eq!(
+                decode(&encode(&decoded, precision), precision).unwrap(),
+                decoded
+            );
+        }
+    }
+
+    #[test]
+    fn test_precision_six_is_finer() {
+        let points = [(50.836_512_3, -0.779_876_5), (50.836_598_7, -0.779_801_2)];
+
+        let five = decode(&encode(&points, GOOGLE_PRECISION), GOOGLE_PRECISION).unwrap();
+        let six = decode(&encode(&points, OSRM_PRECISION), OSRM_PRECISION).unwrap();
+
+        assert_eq!(five[0], (50.83651, -0.77988));
+        assert_eq!(six[0], (50.836512, -0.779877));
+        // decoding at the wrong precision scales every coordinate by ten
+        let wrong = decode(&encode(&points, OSRM_PRECISION), GOOGLE_PRECISION).unwrap();
+        assert!((wrong[0].0 - 508.36512).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        // a latitude with no longitude after it
+        assert_eq!(
+            decode("_p~iF", GOOGLE_PRECISION),
+            Err(PathError::InvalidPolyline(0))
+        );
+        // cut in the middle of the second point
+        assert_eq!(
+            decode("_p~iF~ps|U_ulL", GOOGLE_PRECISION),
+            Err(PathError::InvalidPolyline(10))
+        );
+        // a continuation chunk with nothing after it
+        assert_eq!(
+            decode("_p~iF~ps|U_", GOOGLE_PRECISION),
+            Err(PathError::InvalidPolyline(10))
+        );
+        // characters below `?`
+        assert_eq!(
+            decode("_p~iF ps|U", GOOGLE_PRECISION),
+            Err(PathError::InvalidPolyline(0))
+        );
+    }
+
+    #[test]
+    fn test_decode_overflow() {
+        // the largest latitude step twice, which no longer fits once the second one is added
+        let mut encoded = String::new();
+        encode_value(i64::MAX, &mut encoded);
+        encode_value(0, &mut encoded);
+        let second = encoded.len();
+        encoded.push_str(&encoded.clone());
+
+        assert_eq!(
+            decode(&encoded[..second], GOOGLE_PRECISION).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            decode(&encoded, GOOGLE_PRECISION),
+            Err(PathError::InvalidPolyline(second))
+        );
+    }
+}
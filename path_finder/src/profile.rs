@@ -0,0 +1,436 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{
+    error::PathError,
+    speed::{maxspeed_from_tags, FALLBACK_SPEED_KMH},
+    turn_cost::{TurnCostModel, UTurn},
+    way_filter::{WayFilter, ROUTABLE_HIGHWAYS},
+};
+
+/// Access tag values that close a way to the mode being checked
+const DENIED_ACCESS: &[&str] = &["no", "private"];
+
+/// Which directions along a way can be travelled, relative to the order of its nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oneway {
+    /// Both directions
+    No,
+    /// Only in the order of the nodes, `oneway=yes`
+    Forward,
+    /// Only against the order of the nodes, `oneway=-1`
+    Backward,
+    /// Not usable at all, e.g. a reversible way when those are excluded
+    Closed,
+}
+
+/// Describes how one kind of traffic uses the road network
+/// Each profile decides which ways are accessible, whether `oneway` applies and how much an edge
+/// costs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub way_filter: WayFilter,
+    /// Access tags checked from the most to the least specific, the first one present decides
+    pub access_tags: Vec<String>,
+    /// Whether the plain `oneway` tag applies to this mode
+    pub respects_oneway: bool,
+    /// Mode specific `oneway` tag which overrides the plain one, e.g. `oneway:bicycle=no`
+    pub oneway_tag: Option<String>,
+    /// Whether `oneway=reversible` ways, whose direction changes during the day, are used in both
+    /// directions
+    /// When not set they are left out of the graph
+    pub allow_reversible: bool,
+    /// Mode used to read turn restrictions, e.g. `restriction:bicycle` and `except=bicycle`
+    /// No turn restrictions apply when not set
+    pub restriction_mode: Option<String>,
+    /// Penalties for turning, used when a search asks for turn costs
+    pub turn_costs: TurnCostModel,
+    /// Multiplier applied to the length of an edge per `highway` value, missing values count as 1
+    pub cost_factors: HashMap<String, f32>,
+    /// Travel speed in `km/h` per `highway` value, used when a way has no speed limit
+    pub default_speeds: HashMap<String, f32>,
+    /// The fastest this mode travels in `km/h`, speed limits above it are capped
+    pub max_speed: f32,
+}
+
+impl Default for Profile {
+    /// Any routable way, `oneway` respected and edges cost their length
+    fn default() -> Self {
+        Profile {
+            name: "default".to_string(),
+            way_filter: WayFilter::default(),
+            access_tags: Vec::new(),
+            respects_oneway: true,
+            oneway_tag: None,
+            allow_reversible: false,
+            restriction_mode: Some("motorcar".to_string()),
+            turn_costs: TurnCostModel::default(),
+            cost_factors: HashMap::new(),
+            default_speeds: road_speeds(),
+            max_speed: MOTORWAY_SPEED,
+        }
+    }
+}
+
+impl Profile {
+    pub fn car() -> Self {
+        Profile {
+            name: "car".to_string(),
+            way_filter: routable_except(&[
+                "track",
+                "pedestrian",
+                "footway",
+                "path",
+                "cycleway",
+                "bridleway",
+                "steps",
+            ]),
+            access_tags: to_strings(&["motorcar", "motor_vehicle", "vehicle", "access"]),
+            respects_oneway: true,
+            oneway_tag: None,
+            allow_reversible: false,
+            restriction_mode: Some("motorcar".to_string()),
+            turn_costs: TurnCostModel::default(),
+            cost_factors: per_highway(&[("service", 1.5), ("living_street", 2.0)]),
+            default_speeds: road_speeds(),
+            max_speed: MOTORWAY_SPEED,
+        }
+    }
+
+    pub fn bicycle() -> Self {
+        Profile {
+            name: "bicycle".to_string(),
+            way_filter: routable_except(&["motorway", "motorway_link"]),
+            access_tags: to_strings(&["bicycle", "vehicle", "access"]),
+            respects_oneway: true,
+            oneway_tag: Some("oneway:bicycle".to_string()),
+            allow_reversible: false,
+            restriction_mode: Some("bicycle".to_string()),
+            turn_costs: TurnCostModel {
+                left_turn_penalty: 2.0,
+                right_turn_penalty: 5.0,
+                u_turn: UTurn::Penalty(15.0),
+                ..Default::default()
+            },
+            cost_factors: per_highway(&[
+                ("cycleway", 0.8),
+                ("trunk", 2.0),
+                ("trunk_link", 2.0),
+                ("primary", 1.5),
+                ("primary_link", 1.5),
+                ("footway", 1.5),
+                ("pedestrian", 1.5),
+                ("steps", 5.0),
+            ]),
+            default_speeds: per_highway(&[
+                ("track", 12.0),
+                ("path", 12.0),
+                ("footway", 8.0),
+                ("pedestrian", 8.0),
+                ("steps", 2.0),
+            ]),
+            max_speed: 18.0,
+        }
+    }
+
+    pub fn foot() -> Self {
+        Profile {
+            name: "foot".to_string(),
+            way_filter: routable_except(&["motorway", "motorway_link"]),
+            access_tags: to_strings(&["foot", "access"]),
+            respects_oneway: false,
+            oneway_tag: Some("oneway:foot".to_string()),
+            allow_reversible: true,
+            restriction_mode: None,
+            turn_costs: TurnCostModel::free(),
+            cost_factors: per_highway(&[("trunk", 1.5), ("trunk_link", 1.5), ("primary", 1.2)]),
+            default_speeds: per_highway(&[("steps", 2.0)]),
+            max_speed: 5.0,
+        }
+    }
+
+    /// Replaces the set of routable ways
+    pub fn with_way_filter(mut self, way_filter: WayFilter) -> Self {
+        self.way_filter = way_filter;
+        self
+    }
+
+    /// Whether this mode can use the way at all
+    pub fn is_accessible(&self, tags: &HashMap<String, String>) -> bool {
+        if !self.way_filter.is_routable(tags) {
+            return false;
+        }
+
+        self.access_tags
+            .iter()
+            .find_map(|tag| tags.get(tag))
+            .is_none_or(|value| !DENIED_ACCESS.contains(&value.as_str()))
+    }
+
+    /// Which directions this mode may follow the way in
+    /// The mode specific tag wins over `oneway`, roundabouts and motorways are one way unless
+    /// tagged otherwise
+    pub fn oneway(&self, tags: &HashMap<String, String>) -> Oneway {
+        if let Some(value) = self.oneway_tag.as_ref().and_then(|tag| tags.get(tag)) {
+            if let Some(oneway) = self.parse_oneway(value) {
+                return oneway;
+            }
+        }
+
+        if !self.respects_oneway {
+            return Oneway::No;
+        }
+
+        if let Some(oneway) = tags
+            .get("oneway")
+            .and_then(|value| self.parse_oneway(value))
+        {
+            return oneway;
+        }
+
+        let is_roundabout = tags
+            .get("junction")
+            .is_some_and(|junction| junction == "roundabout" || junction == "circular");
+        let is_motorway = tags
+            .get("highway")
+            .is_some_and(|highway| highway == "motorway" || highway == "motorway_link");
+
+        if is_roundabout || is_motorway {
+            Oneway::Forward
+        } else {
+            Oneway::No
+        }
+    }
+
+    /// Returns `None` for values that are not part of the `oneway` scheme
+    fn parse_oneway(&self, value: &str) -> Option<Oneway> {
+        match value {
+            "yes" | "1" | "true" => Some(Oneway::Forward),
+            "-1" | "reverse" => Some(Oneway::Backward),
+            "no" | "0" | "false" => Some(Oneway::No),
+            "reversible" | "alternating" if self.allow_reversible => Some(Oneway::No),
+            "reversible" | "alternating" => Some(Oneway::Closed),
+            _ => None,
+        }
+    }
+
+    /// Cost of travelling `distance_km` along the way
+    pub fn edge_cost(&self, tags: &HashMap<String, String>, distance_km: f32) -> f32 {
+        let factor = tags
+            .get("highway")
+            .and_then(|highway| self.cost_factors.get(highway))
+            .copied()
+            .unwrap_or(1.0);
+        distance_km * factor
+    }
+
+    /// Travel speed along the way in `km/h`
+    /// The speed limit is used when tagged, otherwise the default speed of the `highway` class
+    pub fn speed(&self, tags: &HashMap<String, String>) -> f32 {
+        let speed = maxspeed_from_tags(tags).unwrap_or_else(|| {
+            tags.get("highway")
+                .and_then(|highway| self.default_speeds.get(highway))
+                .copied()
+                .unwrap_or(FALLBACK_SPEED_KMH)
+        });
+        speed.min(self.max_speed)
+    }
+}
+
+impl FromStr for Profile {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Profile::default()),
+            "car" => Ok(Profile::car()),
+            "bicycle" => Ok(Profile::bicycle()),
+            "foot" => Ok(Profile::foot()),
+            other => Err(PathError::UnknownProfile(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// UK national speed limit on motorways and dual carriageways, 70 mph
+const MOTORWAY_SPEED: f32 = 112.65;
+
+/// Typical speeds of motor traffic in `km/h` per `highway` value
+fn road_speeds() -> HashMap<String, f32> {
+    per_highway(&[
+        ("motorway", 112.0),
+        ("motorway_link", 64.0),
+        ("trunk", 96.0),
+        ("trunk_link", 56.0),
+        ("primary", 80.0),
+        ("primary_link", 48.0),
+        ("secondary", 64.0),
+        ("secondary_link", 40.0),
+        ("tertiary", 48.0),
+        ("tertiary_link", 32.0),
+        ("unclassified", 40.0),
+        ("residential", 32.0),
+        ("living_street", 10.0),
+        ("service", 16.0),
+        ("track", 16.0),
+        ("pedestrian", 5.0),
+        ("footway", 5.0),
+        ("path", 5.0),
+        ("cycleway", 18.0),
+        ("bridleway", 5.0),
+        ("steps", 3.0),
+    ])
+}
+
+fn routable_except(excluded: &[&str]) -> WayFilter {
+    WayFilter::new(
+        ROUTABLE_HIGHWAYS
+            .iter()
+            .filter(|highway| !excluded.contains(highway))
+            .copied(),
+    )
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn per_highway(values: &[(&str, f32)]) -> HashMap<String, f32> {
+    values
+        .iter()
+        .map(|&(highway, factor)| (highway.to_string(), factor))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::tags;
+
+    #[test]
+    fn test_accessibility() {
+        let footway = tags(&[("highway", "footway")]);
+        let motorway = tags(&[("highway", "motorway")]);
+
+        assert!(!Profile::car().is_accessible(&footway));
+        assert!(Profile::foot().is_accessible(&footway));
+        assert!(Profile::car().is_accessible(&motorway));
+        assert!(!Profile::bicycle().is_accessible(&motorway));
+        assert!(!Profile::foot().is_accessible(&motorway));
+    }
+
+    #[test]
+    fn test_access_tags() {
+        let bus_gate = tags(&[
+            ("highway", "residential"),
+            ("motor_vehicle", "no"),
+            ("access", "yes"),
+        ]);
+        let private = tags(&[
+            ("highway", "service"),
+            ("access", "private"),
+            ("foot", "yes"),
+        ]);
+
+        assert!(!Profile::car().is_accessible(&bus_gate));
+        assert!(Profile::bicycle().is_accessible(&bus_gate));
+        assert!(!Profile::car().is_accessible(&private));
+        assert!(Profile::foot().is_accessible(&private));
+    }
+
+    #[test]
+    fn test_oneway() {
+        let oneway = tags(&[("highway", "residential"), ("oneway", "yes")]);
+        let contraflow = tags(&[
+            ("highway", "residential"),
+            ("oneway", "yes"),
+            ("oneway:bicycle", "no"),
+        ]);
+
+        assert_eq!(Profile::car().oneway(&oneway), Oneway::Forward);
+        assert_eq!(Profile::bicycle().oneway(&oneway), Oneway::Forward);
+        assert_eq!(Profile::foot().oneway(&oneway), Oneway::No);
+        assert_eq!(Profile::car().oneway(&contraflow), Oneway::Forward);
+        assert_eq!(Profile::bicycle().oneway(&contraflow), Oneway::No);
+    }
+
+    #[test]
+    fn test_oneway_values() {
+        let car = Profile::car();
+        let oneway = |value: &str| car.oneway(&tags(&[("highway", "primary"), ("oneway", value)]));
+
+        assert_eq!(oneway("1"), Oneway::Forward);
+        assert_eq!(oneway("true"), Oneway::Forward);
+        assert_eq!(oneway("-1"), Oneway::Backward);
+        assert_eq!(oneway("no"), Oneway::No);
+        assert_eq!(oneway("reversible"), Oneway::Closed);
+        assert_eq!(oneway("unknown"), Oneway::No);
+
+        let car = Profile {
+            allow_reversible: true,
+            ..Profile::car()
+        };
+        assert_eq!(
+            car.oneway(&tags(&[("highway", "primary"), ("oneway", "reversible")])),
+            Oneway::No
+        );
+    }
+
+    #[test]
+    fn test_implied_oneway() {
+        let roundabout = tags(&[("highway", "primary"), ("junction", "roundabout")]);
+        let motorway = tags(&[("highway", "motorway")]);
+        let two_way_motorway = tags(&[("highway", "motorway"), ("oneway", "no")]);
+
+        assert_eq!(Profile::car().oneway(&roundabout), Oneway::Forward);
+        assert_eq!(Profile::bicycle().oneway(&roundabout), Oneway::Forward);
+        assert_eq!(Profile::foot().oneway(&roundabout), Oneway::No);
+        assert_eq!(Profile::car().oneway(&motorway), Oneway::Forward);
+        assert_eq!(Profile::car().oneway(&two_way_motorway), Oneway::No);
+    }
+
+    #[test]
+    fn test_edge_cost() {
+        let cycleway = tags(&[("highway", "cycleway")]);
+        let residential = tags(&[("highway", "residential")]);
+
+        assert_eq!(Profile::bicycle().edge_cost(&cycleway, 1.0), 0.8);
+        assert_eq!(Profile::bicycle().edge_cost(&residential, 1.0), 1.0);
+        assert_eq!(Profile::default().edge_cost(&cycleway, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_speed() {
+        let trunk = tags(&[
+            ("highway", "trunk"),
+            ("maxspeed", "70 mph"),
+            ("maxspeed:type", "GB:nsl_dual"),
+        ]);
+        let residential = tags(&[("highway", "residential")]);
+        let zone = tags(&[("highway", "residential"), ("maxspeed", "20 mph")]);
+
+        assert!((Profile::car().speed(&trunk) - 112.65).abs() < 0.01);
+        assert_eq!(Profile::car().speed(&residential), 32.0);
+        assert!((Profile::car().speed(&zone) - 32.19).abs() < 0.01);
+        assert_eq!(Profile::bicycle().speed(&trunk), 18.0);
+        assert_eq!(Profile::foot().speed(&residential), 5.0);
+        assert_eq!(
+            Profile::car().speed(&tags(&[("highway", "bus_guideway")])),
+            FALLBACK_SPEED_KMH
+        );
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        assert_eq!("bicycle".parse::<Profile>(), Ok(Profile::bicycle()));
+        assert_eq!(
+            "horse".parse::<Profile>(),
+            Err(PathError::UnknownProfile("horse".to_string()))
+        );
+    }
+}
@@ -1,6 +1,77 @@
-use rstar::{RTreeObject, AABB, PointDistance};
-use serde::{Serialize, Deserialize};
+use rstar::{PointDistance, RTreeObject, AABB};
+use serde::{Deserialize, Serialize};
 
+/// Mean radius of the earth in metres, the same as the haversine distances of the graph
+pub const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Point of the sphere at the latitude and longitude, in metres from the centre of the earth
+/// Straight line distances between these grow with the great circle distance, so the nearest is the
+/// same
+pub fn to_position(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [
+        EARTH_RADIUS_M * lat.cos() * lon.cos(),
+        EARTH_RADIUS_M * lat.cos() * lon.sin(),
+        EARTH_RADIUS_M * lat.sin(),
+    ]
+}
+
+/// Great circle distance in metres for a squared straight line distance between two positions
+pub fn chord_2_to_metres(chord_2: f64) -> f64 {
+    2.0 * EARTH_RADIUS_M * (chord_2.sqrt() / (2.0 * EARTH_RADIUS_M)).min(1.0).asin()
+}
+
+/// Squared straight line distance between two positions the great circle distance apart
+pub fn metres_to_chord_2(metres: f64) -> f64 {
+    let half_angle = (metres / (2.0 * EARTH_RADIUS_M)).min(std::f64::consts::FRAC_PI_2);
+    (2.0 * EARTH_RADIUS_M * half_angle.sin()).powi(2)
+}
+
+/// Box around every position of the latitude and longitude ranges, in degrees with longitudes
+/// within -180 to 180
+/// It is larger than the curved patch of the sphere, so nodes in it still have to be checked
+pub fn bbox_envelope(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> AABB<[f64; 3]> {
+    let cos_lat = range_of(f64::cos, min_lat, max_lat, &[0.0], &[]);
+    let cos_lon = range_of(f64::cos, min_lon, max_lon, &[0.0], &[-180.0, 180.0]);
+    let sin_lon = range_of(f64::sin, min_lon, max_lon, &[90.0], &[-90.0]);
+    let sin_lat = range_of(f64::sin, min_lat, max_lat, &[90.0], &[-90.0]);
+
+    // the cosine of a latitude is never negative, so the products are extreme at the ends of the
+    // ranges
+    let product = |a: (f64, f64), b: (f64, f64)| {
+        let products = [a.0 * b.0, a.0 * b.1, a.1 * b.0, a.1 * b.1];
+        let min = products.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = products.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (min, max)
+    };
+    let (x, y) = (product(cos_lat, cos_lon), product(cos_lat, sin_lon));
+
+    AABB::from_corners(
+        [
+            x.0 * EARTH_RADIUS_M,
+            y.0 * EARTH_RADIUS_M,
+            sin_lat.0 * EARTH_RADIUS_M,
+        ],
+        [
+            x.1 * EARTH_RADIUS_M,
+            y.1 * EARTH_RADIUS_M,
+            sin_lat.1 * EARTH_RADIUS_M,
+        ],
+    )
+}
+
+/// Lowest and highest value of `f` between the two angles in degrees,
+/// where `peaks` and `troughs` are the angles it reaches 1 and -1 at
+fn range_of(f: fn(f64) -> f64, from: f64, to: f64, peaks: &[f64], troughs: &[f64]) -> (f64, f64) {
+    let (a, b) = (f(from.to_radians()), f(to.to_radians()));
+    let within = |angles: &[f64]| angles.iter().any(|angle| (from..=to).contains(angle));
+    (
+        if within(troughs) { -1.0 } else { a.min(b) },
+        if within(peaks) { 1.0 } else { a.max(b) },
+    )
+}
+
+/// A graph node indexed by its position on the sphere, see `to_position`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodePoint {
     pub id: u64,
@@ -8,16 +79,156 @@ pub struct NodePoint {
     pub lon: f64,
 }
 
+impl NodePoint {
+    pub fn position(&self) -> [f64; 3] {
+        to_position(self.lat, self.lon)
+    }
+}
+
 impl RTreeObject for NodePoint {
-    type Envelope = AABB<[f64; 2]>;
+    type Envelope = AABB<[f64; 3]>;
 
     fn envelope(&self) -> Self::Envelope {
-        AABB::from_point([self.lat, self.lon])
+        AABB::from_point(self.position())
     }
 }
 
 impl PointDistance for NodePoint {
+    /// Squared straight line distance in square metres to a position from `to_position`
+    fn distance_2(&self, point: &[f64; 3]) -> f64 {
+        let position = self.position();
+        (0..3)
+            .map(|axis| (position[axis] - point[axis]).powi(2))
+            .sum()
+    }
+}
+
+/// A straight piece of road between two nodes, indexed once whichever ways it can be travelled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentLine {
+    pub from: u64,
+    pub to: u64,
+    /// `[lat, lon]` of `from`
+    pub from_point: [f64; 2],
+    /// `[lat, lon]` of `to`
+    pub to_point: [f64; 2],
+}
+
+impl SegmentLine {
+    /// Where the point falls on the segment on a local equirectangular projection,
+    /// as the share of the way from `from` to `to` and the `[lat, lon]` there
+    pub fn project(&self, point: &[f64; 2]) -> (f64, [f64; 2]) {
+        let lon_scale = point[0].to_radians().cos();
+        let (dx, dy) = (
+            (self.to_point[1] - self.from_point[1]) * lon_scale,
+            self.to_point[0] - self.from_point[0],
+        );
+        let (px, py) = (
+            (point[1] - self.from_point[1]) * lon_scale,
+            point[0] - self.from_point[0],
+        );
+
+        let length_2 = dx * dx + dy * dy;
+        let fraction = if length_2 > 0.0 {
+            ((px * dx + py * dy) / length_2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let projected = [
+            self.from_point[0] + fraction * (self.to_point[0] - self.from_point[0]),
+            self.from_point[1] + fraction * (self.to_point[1] - self.from_point[1]),
+        ];
+        (fraction, projected)
+    }
+}
+
+impl RTreeObject for SegmentLine {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(self.from_point, self.to_point)
+    }
+}
+
+impl PointDistance for SegmentLine {
+    /// Squared distance in degrees, the same units as the envelope so the tree can prune with it
     fn distance_2(&self, point: &[f64; 2]) -> f64 {
-        ((self.lat - point[0]).powi(2) + (self.lon - point[1]).powi(2)).sqrt()
+        let (dx, dy) = (
+            self.to_point[0] - self.from_point[0],
+            self.to_point[1] - self.from_point[1],
+        );
+        let (px, py) = (point[0] - self.from_point[0], point[1] - self.from_point[1]);
+        let length_2 = dx * dx + dy * dy;
+        let t = if length_2 > 0.0 {
+            ((px * dx + py * dy) / length_2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (px - t * dx).powi(2) + (py - t * dy).powi(2)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use rstar::{Envelope, PointDistance, AABB};
+
+    use super::{bbox_envelope, chord_2_to_metres, metres_to_chord_2, to_position, NodePoint};
+
+    #[test]
+    fn test_distance_2_is_squared() {
+        let node = NodePoint {
+            id: 1,
+            lat: 50.8,
+            lon: -0.78,
+        };
+
+        let distance_2 = node.distance_2(&to_position(50.8, -0.77));
+
+        // 0.01 degrees of longitude is about 703m at this latitude
+        assert!((distance_2.sqrt() - 702.8).abs() < 0.5);
+        assert!((chord_2_to_metres(distance_2) - distance_2.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_metres_and_chords_round_trip() {
+        for metres in [0.0, 1.0, 250.0, 10_000.0, 5_000_000.0] {
+            assert!(
+                (chord_2_to_metres(metres_to_chord_2(metres)) - metres).abs()
+                    < 1e-6 * metres.max(1.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_bbox_envelope_holds_the_patch() {
+        for (min_lat, min_lon, max_lat, max_lon) in [
+            (50.8, -0.8, 50.85, -0.75),
+            (-10.0, 170.0, 10.0, 180.0),
+            (-5.0, -5.0, 5.0, 5.0),
+        ] {
+            let envelope: AABB<[f64; 3]> = bbox_envelope(min_lat, min_lon, max_lat, max_lon);
+
+            for i in 0..=10 {
+                for j in 0..=10 {
+                    let lat = min_lat + (max_lat - min_lat) * i as f64 / 10.0;
+                    let lon = min_lon + (max_lon - min_lon) * j as f64 / 10.0;
+                    let position = to_position(lat, lon);
+                    // allow for rounding on the faces of the box
+                    let grown = AABB::from_corners(
+                        [
+                            envelope.lower()[0] - 1e-6,
+                            envelope.lower()[1] - 1e-6,
+                            envelope.lower()[2] - 1e-6,
+                        ],
+                        [
+                            envelope.upper()[0] + 1e-6,
+                            envelope.upper()[1] + 1e-6,
+                            envelope.upper()[2] + 1e-6,
+                        ],
+                    );
+                    assert!(grown.contains_point(&position), "{} {}", lat, lon);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::osm_data::Relation;
+
+/// Whether a restriction forbids the listed turn or forbids everything but it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestrictionKind {
+    /// `no_left_turn`, `no_u_turn`, `no_entry`...
+    Prohibitory,
+    /// `only_straight_on`, `only_right_turn`...
+    Mandatory,
+}
+
+/// A `type=restriction` relation turning from one of `from_ways` into one of `to_ways` at
+/// `via_node`
+/// Restrictions with a way as `via` are not supported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnRestriction {
+    pub id: u64,
+    pub from_ways: Vec<u64>,
+    pub via_node: u64,
+    pub to_ways: Vec<u64>,
+    pub kind: RestrictionKind,
+}
+
+impl TurnRestriction {
+    /// Reads the restriction that applies to `mode` (e.g. `motorcar`) from the relation
+    /// `restriction:<mode>` wins over `restriction`, and modes listed in `except` are not
+    /// restricted
+    pub(crate) fn from_relation(relation: &Relation, mode: &str) -> Option<Self> {
+        if relation
+            .tags
+            .get("type")
+            .is_none_or(|value| value != "restriction")
+        {
+            return None;
+        }
+        if relation
+            .tags
+            .get("except")
+            .is_some_and(|except| except.split(';').any(|excepted| excepted.trim() == mode))
+        {
+            return None;
+        }
+
+        let value = relation
+            .tags
+            .get(&format!("restriction:{}", mode))
+            .or_else(|| relation.tags.get("restriction"))?;
+        let kind = if value.starts_with("no_") {
+            RestrictionKind::Prohibitory
+        } else if value.starts_with("only_") {
+            RestrictionKind::Mandatory
+        } else {
+            return None;
+        };
+
+        let members = |role: &str, member_type: &str| -> Vec<u64> {
+            relation
+                .members
+                .iter()
+                .filter(|member| member.role == role && member._type == member_type)
+                .map(|member| member.ref_id)
+                .collect()
+        };
+        let from_ways = members("from", "way");
+        let to_ways = members("to", "way");
+        let via_nodes = members("via", "node");
+
+        if from_ways.is_empty() || to_ways.is_empty() || via_nodes.len() != 1 {
+            return None;
+        }
+
+        Some(TurnRestriction {
+            id: relation.id,
+            from_ways,
+            via_node: via_nodes[0],
+            to_ways,
+            kind,
+        })
+    }
+
+    /// Whether turning from `from_way` into `to_way` at the via node is forbidden by this
+    /// restriction
+    pub fn forbids(&self, from_way: u64, to_way: u64) -> bool {
+        if !self.from_ways.contains(&from_way) {
+            return false;
+        }
+
+        match self.kind {
+            RestrictionKind::Prohibitory => self.to_ways.contains(&to_way),
+            RestrictionKind::Mandatory => !self.to_ways.contains(&to_way),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::tests::tags, osm_data::RelationMember};
+
+    fn relation(pairs: &[(&str, &str)], members: &[(&str, u64, &str)]) -> Relation {
+        Relation {
+            id: 1,
+            members: members
+                .iter()
+                .map(|&(member_type, ref_id, role)| RelationMember {
+                    _type: member_type.to_string(),
+                    ref_id,
+                    role: role.to_string(),
+                })
+                .collect(),
+            tags: tags(pairs),
+        }
+    }
+
+    #[test]
+    fn test_prohibitory_restriction() {
+        let relation = relation(
+            &[("type", "restriction"), ("restriction", "no_left_turn")],
+            &[("way", 10, "from"), ("node", 5, "via"), ("way", 11, "to")],
+        );
+        let restriction = TurnRestriction::from_relation(&relation, "motorcar").unwrap();
+
+        assert_eq!(restriction.via_node, 5);
+        assert_eq!(restriction.kind, RestrictionKind::Prohibitory);
+        assert!(restriction.forbids(10, 11));
+        assert!(!restriction.forbids(10, 12));
+        assert!(!restriction.forbids(12, 11));
+    }
+
+    #[test]
+    fn test_mandatory_restriction() {
+        let relation = relation(
+            &[("type", "restriction"), ("restriction", "only_straight_on")],
+            &[("way", 10, "from"), ("node", 5, "via"), ("way", 11, "to")],
+        );
+        let restriction = TurnRestriction::from_relation(&relation, "motorcar").unwrap();
+
+        assert_eq!(restriction.kind, RestrictionKind::Mandatory);
+        assert!(!restriction.forbids(10, 11));
+        assert!(restriction.forbids(10, 12));
+        assert!(!restriction.forbids(12, 13));
+    }
+
+    #[test]
+    fn test_restriction_modes() {
+        let members = [("way", 10, "from"), ("node", 5, "via"), ("way", 11, "to")];
+        let except_bicycle = relation(
+            &[
+                ("type", "restriction"),
+                ("restriction", "no_right_turn"),
+                ("except", "psv;bicycle"),
+            ],
+            &members,
+        );
+        let bicycle_only = relation(
+            &[
+                ("type", "restriction"),
+                ("restriction:bicycle", "no_right_turn"),
+            ],
+            &members,
+        );
+
+        assert!(TurnRestriction::from_relation(&except_bicycle, "motorcar").is_some());
+        assert!(TurnRestriction::from_relation(&except_bicycle, "bicycle").is_none());
+        assert!(TurnRestriction::from_relation(&bicycle_only, "bicycle").is_some());
+        assert!(TurnRestriction::from_relation(&bicycle_only, "motorcar").is_none());
+    }
+
+    #[test]
+    fn test_unsupported_relations() {
+        let route = relation(&[("type", "route")], &[("way", 10, "")]);
+        let via_way = relation(
+            &[("type", "restriction"), ("restriction", "no_u_turn")],
+            &[("way", 10, "from"), ("way", 12, "via"), ("way", 11, "to")],
+        );
+
+        assert!(TurnRestriction::from_relation(&route, "motorcar").is_none());
+        assert!(TurnRestriction::from_relation(&via_way, "motorcar").is_none());
+    }
+}
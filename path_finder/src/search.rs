@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    algorithm::Algorithm,
+    graph::Route,
+    hull::{HullKind, DEFAULT_CONCAVITY},
+    metric::Metric,
+    trace::SearchTrace,
+};
+
+/// Options for a single path query
+/// Deserialises from JS objects like `{ algorithm: "a_star", metric: "fastest" }`, missing fields
+/// use the defaults
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub algorithm: Algorithm,
+    pub metric: Metric,
+    /// Records the exploration order of the search in the result
+    pub trace: bool,
+    /// Routes as if the graph had no turn restrictions
+    pub ignore_turn_restrictions: bool,
+    /// Adds the turn penalties of the graph, so paths avoid needless turns and U-turns
+    pub turn_costs: bool,
+    /// Adds the geometry of the route as a polyline encoded at this precision, 5 or 6
+    pub polyline: Option<u32>,
+}
+
+/// The route found by a query, with the exploration order when it was requested
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub route: Route,
+    pub trace: Option<SearchTrace>,
+}
+
+/// Options for `Graph::alternative_routes`
+/// Deserialises from JS objects like `{ count: 2, max_overlap: 0.5 }`, missing fields use the
+/// defaults
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AlternativeOptions {
+    pub metric: Metric,
+    /// Most alternatives returned besides the shortest path
+    pub count: usize,
+    /// Highest share of its length an alternative may have in common with any path before it
+    pub max_overlap: f64,
+    /// How many of the k shortest paths are considered before giving up on finding more
+    /// alternatives
+    /// Each one costs a few Dijkstra searches, so it is capped at `MAX_CANDIDATES`
+    pub candidates: usize,
+    /// Also consider paths that take turns forbidden by the turn restrictions of the graph
+    pub ignore_turn_restrictions: bool,
+}
+
+/// Most paths of Yen's algorithm considered for alternatives, whatever
+/// `AlternativeOptions::candidates` asks for
+pub const MAX_CANDIDATES: usize = 50;
+
+impl Default for AlternativeOptions {
+    fn default() -> Self {
+        AlternativeOptions {
+            metric: Metric::default(),
+            count: 2,
+            max_overlap: 0.6,
+            candidates: 10,
+            ignore_turn_restrictions: false,
+        }
+    }
+}
+
+/// Options for `Graph::isochrone`
+/// Deserialises from JS objects like
+/// `{ metric: "fastest", thresholds: [300, 600], hull: "concave" }`, missing fields use the
+/// defaults
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct IsochroneOptions {
+    pub metric: Metric,
+    /// Highest costs of the bands, in `km` of road for the shortest metric and seconds for the
+    /// fastest
+    /// Distances are the lengths of the roads, not the weights the profile gives them for routing
+    pub thresholds: Vec<f32>,
+    /// Outline drawn around the nodes of each band
+    pub hull: HullKind,
+    /// How closely a concave hull follows the nodes, lower is closer
+    pub concavity: f64,
+}
+
+impl Default for IsochroneOptions {
+    fn default() -> Self {
+        IsochroneOptions {
+            metric: Metric::default(),
+            thresholds: Vec::new(),
+            hull: HullKind::default(),
+            concavity: DEFAULT_CONCAVITY,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+const KMH_PER_MPH: f32 = 1.609344;
+const KMH_PER_KNOT: f32 = 1.852;
+
+/// Speed used for ways whose `highway` value has no default speed in the profile
+pub const FALLBACK_SPEED_KMH: f32 = 30.0;
+
+/// Returns the speed limit of the way in `km/h`
+/// Looks at `maxspeed` first and falls back to the implicit limits in `maxspeed:type`,
+/// `source:maxspeed` and `zone:maxspeed` (e.g. `GB:nsl_dual`)
+pub fn maxspeed_from_tags(tags: &HashMap<String, String>) -> Option<f32> {
+    [
+        "maxspeed",
+        "maxspeed:type",
+        "source:maxspeed",
+        "zone:maxspeed",
+    ]
+    .iter()
+    .filter_map(|key| tags.get(*key))
+    .find_map(|value| parse_maxspeed(value))
+}
+
+/// Parses a `maxspeed` value into `km/h`
+/// Handles plain numbers (`km/h`), `mph` and `knots` units, `walk` and the UK national limit codes
+/// Returns `None` for values without a fixed limit such as `none`, `signals` or `variable`
+pub fn parse_maxspeed(value: &str) -> Option<f32> {
+    // multiple limits are separated by `;`, the lowest one is the safe choice
+    if value.contains(';') {
+        return value
+            .split(';')
+            .filter_map(parse_maxspeed)
+            .min_by(|a, b| a.total_cmp(b));
+    }
+
+    let value = value.trim();
+    if value.contains(':') {
+        return zone_speed(value);
+    }
+    if value == "walk" {
+        return Some(6.0);
+    }
+
+    let number_end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    // a limit of 0 would make every edge on the way take forever
+    let speed: f32 = value[..number_end]
+        .parse()
+        .ok()
+        .filter(|&speed: &f32| speed > 0.0)?;
+
+    match value[number_end..].trim().to_lowercase().as_str() {
+        "" | "km/h" | "kmh" | "kph" => Some(speed),
+        "mph" => Some(speed * KMH_PER_MPH),
+        "knots" => Some(speed * KMH_PER_KNOT),
+        _ => None,
+    }
+}
+
+/// Implicit speed limits, in `km/h`, of the zone codes used in the UK
+fn zone_speed(code: &str) -> Option<f32> {
+    let mph = match code {
+        "GB:motorway" | "GB:nsl_dual" => 70.0,
+        "GB:nsl_single" => 60.0,
+        "GB:nsl_restricted" | "GB:urban" => 30.0,
+        "GB:zone20" => 20.0,
+        "GB:zone30" => 30.0,
+        _ => return None,
+    };
+    Some(mph * KMH_PER_MPH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::tags;
+
+    #[test]
+    fn test_parse_maxspeed() {
+        assert_eq!(parse_maxspeed("50"), Some(50.0));
+        assert_eq!(parse_maxspeed("50 km/h"), Some(50.0));
+        assert_eq!(parse_maxspeed("30 mph"), Some(30.0 * KMH_PER_MPH));
+        assert_eq!(parse_maxspeed("70mph"), Some(70.0 * KMH_PER_MPH));
+        assert_eq!(parse_maxspeed("5 knots"), Some(5.0 * KMH_PER_KNOT));
+        assert_eq!(parse_maxspeed("20 mph;30 mph"), Some(20.0 * KMH_PER_MPH));
+        assert_eq!(parse_maxspeed("walk"), Some(6.0));
+        assert_eq!(parse_maxspeed("GB:nsl_single"), Some(60.0 * KMH_PER_MPH));
+        assert_eq!(parse_maxspeed("none"), None);
+        assert_eq!(parse_maxspeed("signals"), None);
+        assert_eq!(parse_maxspeed("50 furlongs"), None);
+        assert_eq!(parse_maxspeed("0"), None);
+        assert_eq!(parse_maxspeed("0 mph"), None);
+        assert_eq!(parse_maxspeed("0;30"), Some(30.0));
+    }
+
+    #[test]
+    fn test_maxspeed_from_tags() {
+        // the trunk road from test.osm
+        let trunk = tags(&[
+            ("highway", "trunk"),
+            ("maxspeed", "70 mph"),
+            ("maxspeed:type", "GB:nsl_dual"),
+        ]);
+        let implicit = tags(&[("highway", "trunk"), ("maxspeed:type", "GB:nsl_dual")]);
+        let unlimited = tags(&[
+            ("highway", "trunk"),
+            ("maxspeed", "none"),
+            ("source:maxspeed", "GB:nsl_single"),
+        ]);
+
+        assert_eq!(maxspeed_from_tags(&trunk), Some(70.0 * KMH_PER_MPH));
+        assert_eq!(maxspeed_from_tags(&implicit), Some(70.0 * KMH_PER_MPH));
+        assert_eq!(maxspeed_from_tags(&unlimited), Some(60.0 * KMH_PER_MPH));
+        assert_eq!(
+            maxspeed_from_tags(&tags(&[("highway", "residential")])),
+            None
+        );
+    }
+}
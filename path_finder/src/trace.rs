@@ -0,0 +1,71 @@
+use serde::Serialize;
+
+use crate::graph::Graph;
+
+/// A node taken off the frontier with its final cost from the start
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettledNode {
+    pub id: u64,
+    pub lat: f64,
+    pub lon: f64,
+    pub cost: f32,
+}
+
+/// An edge that improved the tentative cost of its `to` node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelaxedEdge {
+    pub from: u64,
+    pub to: u64,
+    pub from_lat: f64,
+    pub from_lon: f64,
+    pub to_lat: f64,
+    pub to_lon: f64,
+    pub cost: f32,
+}
+
+/// The order in which a search explored the graph, used to animate the frontier
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct SearchTrace {
+    pub settled: Vec<SettledNode>,
+    pub relaxed: Vec<RelaxedEdge>,
+}
+
+/// Records into a `SearchTrace` when one is requested and does nothing otherwise
+pub(crate) struct Tracer<'a> {
+    graph: &'a Graph,
+    trace: Option<&'a mut SearchTrace>,
+}
+
+impl<'a> Tracer<'a> {
+    pub(crate) fn new(graph: &'a Graph, trace: Option<&'a mut SearchTrace>) -> Self {
+        Tracer { graph, trace }
+    }
+
+    pub(crate) fn settled(&mut self, id: u64, cost: f32) {
+        if let (Some(trace), Some((lat, lon))) =
+            (self.trace.as_deref_mut(), self.graph.coordinates(id))
+        {
+            trace.settled.push(SettledNode { id, lat, lon, cost });
+        }
+    }
+
+    pub(crate) fn relaxed(&mut self, from: u64, to: u64, cost: f32) {
+        let Some(trace) = self.trace.as_deref_mut() else {
+            return;
+        };
+
+        if let (Some((from_lat, from_lon)), Some((to_lat, to_lon))) =
+            (self.graph.coordinates(from), self.graph.coordinates(to))
+        {
+            trace.relaxed.push(RelaxedEdge {
+                from,
+                to,
+                from_lat,
+                from_lon,
+                to_lat,
+                to_lon,
+                cost,
+            });
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{metric::Metric, speed::FALLBACK_SPEED_KMH};
+
+/// What a path may do when it turns back along the edge it arrived on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UTurn {
+    Allowed,
+    /// Extra seconds for turning back
+    Penalty(f32),
+    Forbidden,
+}
+
+/// Penalties for turning at a node, in seconds
+/// The shortest metric counts them as the distance covered in that time at `FALLBACK_SPEED_KMH`
+/// Deserialises from JS objects like `{ right_turn_penalty: 15, u_turn: "forbidden" }`, missing
+/// fields use the defaults
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnCostModel {
+    /// Turns of up to this many degrees either side of straight on are free
+    pub straight_angle: f64,
+    /// Seconds for a right angle turn to the left, sharper turns cost more and gentler ones less
+    pub left_turn_penalty: f32,
+    /// Seconds for a right angle turn to the right, these cross the oncoming traffic in the UK
+    pub right_turn_penalty: f32,
+    pub u_turn: UTurn,
+}
+
+impl Default for TurnCostModel {
+    fn default() -> Self {
+        TurnCostModel {
+            straight_angle: 30.0,
+            left_turn_penalty: 5.0,
+            right_turn_penalty: 10.0,
+            u_turn: UTurn::Penalty(60.0),
+        }
+    }
+}
+
+impl TurnCostModel {
+    /// Every turn costs nothing and turning back is allowed
+    pub fn free() -> Self {
+        TurnCostModel {
+            straight_angle: 180.0,
+            left_turn_penalty: 0.0,
+            right_turn_penalty: 0.0,
+            u_turn: UTurn::Allowed,
+        }
+    }
+
+    /// Penalty in seconds for a turn of `angle` degrees, negative to the left and positive to the
+    /// right
+    pub fn penalty(&self, angle: f64) -> f32 {
+        let excess = angle.abs() - self.straight_angle;
+        if excess <= 0.0 {
+            return 0.0;
+        }
+
+        let per_right_angle = if angle < 0.0 {
+            self.left_turn_penalty
+        } else {
+            self.right_turn_penalty
+        };
+        per_right_angle * (excess / (90.0 - self.straight_angle).max(1.0)) as f32
+    }
+
+    /// Cost of going `from` -> `via` -> `to` for the metric, `None` when the turn is forbidden
+    /// A U-turn is going straight back to `from`
+    pub(crate) fn turn_cost(
+        &self,
+        from: (u64, f64, f64),
+        via: (f64, f64),
+        to: (u64, f64, f64),
+        metric: Metric,
+    ) -> Option<f32> {
+        let seconds = if from.0 == to.0 {
+            match self.u_turn {
+                UTurn::Allowed => 0.0,
+                UTurn::Penalty(seconds) => seconds,
+                UTurn::Forbidden => return None,
+            }
+        } else {
+            self.penalty(turn_angle((from.1, from.2), via, (to.1, to.2)))
+        };
+
+        Some(match metric {
+            Metric::Shortest => seconds * FALLBACK_SPEED_KMH / 3600.0,
+            Metric::Fastest => seconds,
+        })
+    }
+}
+
+/// Angle in degrees between heading `from` -> `via` and heading `via` -> `to`
+/// Negative is a turn to the left, positive to the right, and 180 is turning back
+pub fn turn_angle(from: (f64, f64), via: (f64, f64), to: (f64, f64)) -> f64 {
+    let angle = bearing(via, to) - bearing(from, via);
+    // into (-180, 180]
+    let angle = (angle + 180.0).rem_euclid(360.0) - 180.0;
+    if angle == -180.0 {
+        180.0
+    } else {
+        angle
+    }
+}
+
+/// Compass bearing in degrees from `from` to `to`, accurate enough over the length of an edge
+pub fn bearing(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let d_lat = to.0 - from.0;
+    let d_lon = (to.1 - from.1) * from.0.to_radians().cos();
+    d_lon.atan2(d_lat).to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIA: (f64, f64) = (50.8365, -0.78);
+    const WEST: (f64, f64) = (50.8365, -0.7814);
+    const EAST: (f64, f64) = (50.8365, -0.7786);
+    const NORTH: (f64, f64) = (50.8374, -0.78);
+    const SOUTH: (f64, f64) = (50.8356, -0.78);
+
+    #[test]
+    fn test_turn_angle() {
+        // heading east
+        assert!(turn_angle(WEST, VIA, EAST).abs() < 1.0);
+        assert!((turn_angle(WEST, VIA, NORTH) + 90.0).abs() < 1.0);
+        assert!((turn_angle(WEST, VIA, SOUTH) - 90.0).abs() < 1.0);
+        assert!((turn_angle(WEST, VIA, WEST) - 180.0).abs() < 1e-9);
+        // heading south
+        assert!((turn_angle(NORTH, VIA, EAST) + 90.0).abs() < 1.0);
+        assert!((turn_angle(NORTH, VIA, WEST) - 90.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_penalty() {
+        let model = TurnCostModel::default();
+
+        assert_eq!(model.penalty(10.0), 0.0);
+        assert_eq!(model.penalty(-30.0), 0.0);
+        assert_eq!(model.penalty(-90.0), 5.0);
+        assert_eq!(model.penalty(90.0), 10.0);
+        assert_eq!(model.penalty(150.0), 20.0);
+        assert_eq!(TurnCostModel::free().penalty(170.0), 0.0);
+    }
+
+    #[test]
+    fn test_turn_cost() {
+        let model = TurnCostModel::default();
+        let (from, to) = ((1, WEST.0, WEST.1), (2, SOUTH.0, SOUTH.1));
+
+        let seconds = model.turn_cost(from, VIA, to, Metric::Fastest).unwrap();
+        assert!((seconds - 10.0).abs() < 0.1);
+        let km = model.turn_cost(from, VIA, to, Metric::Shortest).unwrap();
+        assert!((km - seconds * FALLBACK_SPEED_KMH / 3600.0).abs() < 1e-6);
+
+        assert_eq!(
+            model.turn_cost(from, VIA, from, Metric::Fastest),
+            Some(60.0)
+        );
+        let forbidden = TurnCostModel {
+            u_turn: UTurn::Forbidden,
+            ..model
+        };
+        assert_eq!(forbidden.turn_cost(from, VIA, from, Metric::Fastest), None);
+        let allowed = TurnCostModel {
+            u_turn: UTurn::Allowed,
+            ..model
+        };
+        assert_eq!(
+            allowed.turn_cost(from, VIA, from, Metric::Fastest),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let model: TurnCostModel =
+            serde_json::from_str(r#"{ "right_turn_penalty": 15, "u_turn": "forbidden" }"#).unwrap();
+
+        assert_eq!(model.right_turn_penalty, 15.0);
+        assert_eq!(model.left_turn_penalty, 5.0);
+        assert_eq!(model.u_turn, UTurn::Forbidden);
+
+        let model: TurnCostModel =
+            serde_json::from_str(r#"{ "u_turn": { "penalty": 30 } }"#).unwrap();
+        assert_eq!(model.u_turn, UTurn::Penalty(30.0));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+
+/// `highway` values that can be travelled along by at least one kind of traffic
+pub const ROUTABLE_HIGHWAYS: &[&str] = &[
+    "motorway",
+    "motorway_link",
+    "trunk",
+    "trunk_link",
+    "primary",
+    "primary_link",
+    "secondary",
+    "secondary_link",
+    "tertiary",
+    "tertiary_link",
+    "unclassified",
+    "residential",
+    "living_street",
+    "service",
+    "road",
+    "track",
+    "pedestrian",
+    "footway",
+    "path",
+    "cycleway",
+    "bridleway",
+    "steps",
+];
+
+/// Decides which OSM ways are turned into edges of the graph
+/// A way is routable when its `highway` value is allowed and it is not an area
+#[derive(Debug, Clone, PartialEq)]
+pub struct WayFilter {
+    highways: HashSet<String>,
+}
+
+impl Default for WayFilter {
+    fn default() -> Self {
+        WayFilter::new(ROUTABLE_HIGHWAYS.iter().copied())
+    }
+}
+
+impl WayFilter {
+    /// Creates a filter allowing only the given `highway` values
+    pub fn new<I, S>(highways: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        WayFilter {
+            highways: highways.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Adds a `highway` value to the allowed set
+    pub fn allow(mut self, highway: &str) -> Self {
+        self.highways.insert(highway.to_string());
+        self
+    }
+
+    /// Removes a `highway` value from the allowed set
+    pub fn deny(mut self, highway: &str) -> Self {
+        self.highways.remove(highway);
+        self
+    }
+
+    pub fn is_routable(&self, tags: &HashMap<String, String>) -> bool {
+        let is_area = tags.get("area").is_some_and(|v| v == "yes");
+        !is_area
+            && tags
+                .get("highway")
+                .is_some_and(|highway| self.highways.contains(highway))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::tags;
+
+    #[test]
+    fn test_default_filter() {
+        let filter = WayFilter::default();
+
+        assert!(filter.is_routable(&tags(&[("highway", "trunk"), ("ref", "A27")])));
+        assert!(filter.is_routable(&tags(&[("highway", "footway")])));
+        assert!(!filter.is_routable(&tags(&[("building", "yes")])));
+        assert!(!filter.is_routable(&tags(&[("landuse", "retail")])));
+        assert!(!filter.is_routable(&tags(&[("highway", "proposed")])));
+        assert!(!filter.is_routable(&tags(&[("highway", "pedestrian"), ("area", "yes")])));
+    }
+
+    #[test]
+    fn test_override_filter() {
+        let filter = WayFilter::new(["primary"]).allow("construction");
+
+        assert!(filter.is_routable(&tags(&[("highway", "primary")])));
+        assert!(filter.is_routable(&tags(&[("highway", "construction")])));
+        assert!(!filter.is_routable(&tags(&[("highway", "footway")])));
+
+        let filter = WayFilter::default().deny("steps");
+        assert!(!filter.is_routable(&tags(&[("highway", "steps")])));
+    }
+}
@@ -6,6 +6,7 @@ import Modal from "./components/modal/Modal";
 import { useGraph } from "./hooks/useGraph";
 import { Icons } from "./components/ui/icons";
 import { useLocation } from "./hooks/useLocation";
+import { Algorithm } from "./global";
 
 function App() {
   const [showModal, setShowModal] = useState<boolean>(false);
@@ -21,11 +22,11 @@ function App() {
     setShowModal((prev) => !prev)
   };
 
-  const handleComputePath = () => {
+  const handleComputePath = ({ algorithm }: { algorithm: Algorithm }) => {
     if (start && end && pathFn) {
-      console.log("Computing shortest path...");
+      console.log(`Computing shortest path with ${algorithm}...`);
 
-      const result = pathFn(start.lat, start.lng, end.lat, end.lng);
+      const result = pathFn(start.lat, start.lng, end.lat, end.lng, algorithm);
       // console.log("Res: ", result);
       if (result) {
         // console.log("Computed Path:", result);
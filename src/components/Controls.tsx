@@ -8,6 +8,7 @@ import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@
 import { Form, FormControl, FormField, FormItem, FormLabel, FormMessage } from "@/components/ui/form";
 import { Accordion, AccordionContent, AccordionItem, AccordionTrigger } from "@/components/ui/accordion";
 import { Label } from "@/components/ui/label"
+import { Algorithm } from "@/global";
 
 const formSchema = z.object({
     startLat: z.coerce
@@ -26,7 +27,7 @@ const formSchema = z.object({
         .number()
         .min(-180)
         .max(180, "Longitude must be between -180 and 180"),
-    algorithm: z.enum(["dijkstra", "a_star", "bellman_ford"], {
+    algorithm: z.enum(["dijkstra", "a_star", "bellman_ford", "bidirectional"], {
         required_error: "Please select an algorithm",
     }),
 });
@@ -36,7 +37,7 @@ const Controls: React.FC<{
     end: { lat: number; lng: number } | null;
     setStart: (location: { lat: number; lng: number }) => void;
     setEnd: (location: { lat: number; lng: number }) => void;
-    onVisualize: () => void;
+    onVisualize: (values: { algorithm: Algorithm }) => void;
   }> = ({ start, end, setStart, setEnd, onVisualize }) => {
     const form = useForm<z.infer<typeof formSchema>>({
         resolver: zodResolver(formSchema),
@@ -165,6 +166,7 @@ const Controls: React.FC<{
                                     <SelectItem value="dijkstra">Dijkstra</SelectItem>
                                     <SelectItem value="a_star">A*Star</SelectItem>
                                     <SelectItem value="bellman_ford">Bellman Ford</SelectItem>
+                                    <SelectItem value="bidirectional">Bidirectional Dijkstra</SelectItem>
                                 </SelectContent>
                             </Select>
                             </div>
@@ -9,6 +9,8 @@ export interface PathPoint extends Location {
     id: number,
 }
 
+export type Algorithm = "dijkstra" | "a_star" | "bellman_ford" | "bidirectional";
+
 export const BOUNDS = {
     maxLat: 50.84156,
     minLat: 50.8254,
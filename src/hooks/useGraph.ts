@@ -1,22 +1,29 @@
-import { PathPoint } from "@/global";
+import { Algorithm, PathPoint } from "@/global";
 import init, {load_graph, find_shortest_path, is_graph_loaded} from "path_finder";
 import { useEffect, useState } from "react";
 
 export function useGraph() {
     const [graphState, setGraphState] = useState<boolean>(false);
-    const [pathFn, setPathFn] = useState<undefined | ((lat1: number, lon1: number, lat2: number, lon2: number) => PathPoint[] | null)>(undefined);
+    const [pathFn, setPathFn] = useState<undefined | ((lat1: number, lon1: number, lat2: number, lon2: number, algorithm: Algorithm) => PathPoint[] | null)>(undefined);
     const [loadGraphFn, setLoadGraphFn] = useState<null | ((json_data: string) => void)>(null);
 
     useEffect(() => {
         (async () => {
             await init();
-            setPathFn(() => (lat1: number, lon1: number, lat2: number, lon2: number) => {
-                const result = find_shortest_path(lat1, lon1, lat2, lon2);
-                if (result && Array.isArray(result)) {
-                    return result.map(node => ({
-                        id: node[0],    // Node ID
-                        lat: node[1],   // Latitude
-                        lng: node[2],   // Longitude
+            setPathFn(() => (lat1: number, lon1: number, lat2: number, lon2: number, algorithm: Algorithm) => {
+                let result;
+                try {
+                    result = find_shortest_path(lat1, lon1, lat2, lon2, { algorithm });
+                } catch (error) {
+                    // e.g. an algorithm that can't apply the turn restrictions of the graph
+                    console.error(error);
+                    return null;
+                }
+                if (result && Array.isArray(result.route?.nodes)) {
+                    return result.route.nodes.map((node: { id: number; lat: number; lon: number }) => ({
+                        id: node.id,
+                        lat: node.lat,
+                        lng: node.lon,
                     }));
                 }
                 return null;